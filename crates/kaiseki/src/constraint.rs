@@ -0,0 +1,191 @@
+use crate::error::{Error, Result};
+
+use mecab_types::pos::PartOfSpeech;
+
+/// Known segmentation of parts of an input, nodes crossing a constraint are removed from
+/// the lattice before the best path is searched.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Constraints {
+    /// Byte offsets where a morpheme must start or end.
+    pub boundaries: Vec<usize>,
+    /// Byte ranges that must be tokenized as a single morpheme.
+    pub spans: Vec<ForcedSpan>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForcedSpan {
+    pub start: usize,
+    pub end: usize,
+    pub part_of_speech: Option<PartOfSpeech>,
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_boundary(mut self, index: usize) -> Self {
+        self.boundaries.push(index);
+        self
+    }
+
+    pub fn with_span(mut self, start: usize, end: usize, pos: Option<PartOfSpeech>) -> Self {
+        self.spans.push(ForcedSpan {
+            start,
+            end,
+            part_of_speech: pos,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boundaries.is_empty() && self.spans.is_empty()
+    }
+
+    pub(crate) fn validate(&self, input: &str) -> Result<()> {
+        for &index in &self.boundaries {
+            if !input.is_char_boundary(index) {
+                return Err(Error::InvalidConstraint(format!(
+                    "Boundary {} is not a character boundary",
+                    index
+                )));
+            }
+        }
+
+        for (i, span) in self.spans.iter().enumerate() {
+            let is_valid = span.start < span.end
+                && input.is_char_boundary(span.start)
+                && input.is_char_boundary(span.end);
+
+            if !is_valid {
+                return Err(Error::InvalidConstraint(format!(
+                    "Invalid span {}..{}",
+                    span.start, span.end
+                )));
+            }
+
+            let overlapping = self.spans[i + 1..]
+                .iter()
+                .find(|other| span.start < other.end && other.start < span.end);
+
+            if let Some(other) = overlapping {
+                return Err(Error::InvalidConstraint(format!(
+                    "Span {}..{} overlaps {}..{}",
+                    span.start, span.end, other.start, other.end
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Maps the offsets to another text, failing when an offset has no equivalent.
+    pub(crate) fn map_offsets<F>(&self, map: F) -> Result<Self>
+    where
+        F: Fn(usize) -> Option<usize>,
+    {
+        let map = |index: usize| {
+            map(index).ok_or_else(|| {
+                Error::InvalidConstraint(format!(
+                    "Offset {} is inside a normalized character",
+                    index
+                ))
+            })
+        };
+        let boundaries = self
+            .boundaries
+            .iter()
+            .map(|&index| map(index))
+            .collect::<Result<_>>()?;
+        let spans = self
+            .spans
+            .iter()
+            .map(|span| {
+                Ok(ForcedSpan {
+                    start: map(span.start)?,
+                    end: map(span.end)?,
+                    part_of_speech: span.part_of_speech.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { boundaries, spans })
+    }
+
+    /// Checks that a node doesn't cross a boundary or partially cover a span.
+    pub(crate) fn allows(&self, start: usize, end: usize) -> bool {
+        let crosses_boundary = self
+            .boundaries
+            .iter()
+            .any(|&index| start < index && index < end);
+        let crosses_span = self.spans.iter().any(|span| {
+            let overlaps = start < span.end && span.start < end;
+            overlaps && (start, end) != (span.start, span.end)
+        });
+
+        !crosses_boundary && !crosses_span
+    }
+
+    pub(crate) fn get_span(&self, start: usize, end: usize) -> Option<&ForcedSpan> {
+        self.spans
+            .iter()
+            .find(|span| span.start == start && span.end == end)
+    }
+
+    pub(crate) fn span_starting_at(&self, start: usize) -> Option<&ForcedSpan> {
+        self.spans.iter().find(|span| span.start == start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let constraints =
+            Constraints::new()
+                .with_boundary(3)
+                .with_span(6, 12, Some(PartOfSpeech::Noun));
+
+        assert!(constraints.allows(0, 3));
+        assert!(!constraints.allows(0, 6));
+        assert!(constraints.allows(3, 6));
+        assert!(constraints.allows(6, 12));
+        assert!(!constraints.allows(6, 9));
+        assert!(!constraints.allows(9, 15));
+        assert!(!constraints.allows(3, 15));
+        assert!(constraints.allows(12, 15));
+    }
+
+    #[test]
+    fn test_validate() {
+        let input = "東京都に住む";
+
+        assert!(Constraints::new().with_boundary(3).validate(input).is_ok());
+        assert!(Constraints::new().with_boundary(4).validate(input).is_err());
+        assert!(Constraints::new()
+            .with_span(6, 3, None)
+            .validate(input)
+            .is_err());
+        assert!(Constraints::new()
+            .with_span(0, 30, None)
+            .validate(input)
+            .is_err());
+        assert!(Constraints::new()
+            .with_span(0, 6, None)
+            .with_span(3, 9, None)
+            .validate(input)
+            .is_err());
+    }
+
+    #[test]
+    fn test_map_offsets() {
+        let constraints = Constraints::new().with_boundary(6).with_span(0, 6, None);
+        let mapped = constraints.map_offsets(|index| Some(index / 2)).unwrap();
+
+        assert_eq!(vec![3], mapped.boundaries);
+        assert_eq!((0, 3), (mapped.spans[0].start, mapped.spans[0].end));
+        assert!(constraints.map_offsets(|_| None).is_err());
+    }
+}
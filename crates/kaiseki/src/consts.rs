@@ -1,34 +1,57 @@
+pub const TERM_FST_FILE: &str = "term.fst";
+pub const UNK_DICT_FILE: &str = "unk.bin";
+pub const TERM_DICT_FILE: &str = "dict.bin";
+pub const CHAR_TABLE_FILE: &str = "char.bin";
+pub const COST_MATRIX_FILE: &str = "matrix.bin";
+pub const METADATA_FILE: &str = "meta.bin";
+
+#[cfg(feature = "embedded")]
 pub const TERM_FST: &[u8] = include_bytes!("../../../data/term.fst");
+#[cfg(feature = "embedded")]
 pub const UNK_DICT: &[u8] = include_bytes!("../../../data/unk.bin");
+#[cfg(feature = "embedded")]
 pub const TERM_DICT: &[u8] = include_bytes!("../../../data/dict.bin");
+#[cfg(feature = "embedded")]
 pub const CHAR_TABLE: &[u8] = include_bytes!("../../../data/char.bin");
+#[cfg(feature = "embedded")]
 pub const COST_MATRIX: &[u8] = include_bytes!("../../../data/matrix.bin");
+#[cfg(feature = "embedded")]
+pub const METADATA: &[u8] = include_bytes!("../../../data/meta.bin");
 
-#[cfg(test)]
+#[cfg(all(test, feature = "embedded"))]
 mod tests {
     use super::*;
 
     use mecab_types::{
-        bincode::decode_slice,
+        blob::Blob,
         char::{CharCategory, CharTable},
         cost::CostMatrix,
+        meta::{DictionaryKind, Metadata},
         unk::UnknownDictionary,
     };
 
+    #[test]
+    fn test_metadata() {
+        let metadata = Metadata::decode(METADATA).unwrap();
+
+        assert_eq!(DictionaryKind::Ipadic, metadata.kind);
+    }
+
     #[test]
     fn test_get_term() {
-        let unk_dict = decode_slice::<UnknownDictionary>(UNK_DICT).unwrap();
+        let unk_dict = UnknownDictionary::decode(UNK_DICT).unwrap();
         let terms = unk_dict.get_terms("DEFAULT").unwrap();
         let (id, term) = &terms[0];
 
         assert_eq!(0, *id);
-        assert_eq!(5, term.context_id);
+        assert_eq!(5, term.left_id);
+        assert_eq!(5, term.right_id);
         assert_eq!(4769, term.cost);
     }
 
     #[test]
     fn test_lookup() {
-        let lookup_table = decode_slice::<CharTable>(CHAR_TABLE).unwrap();
+        let lookup_table = CharTable::decode(CHAR_TABLE).unwrap();
         let categories = lookup_table.lookup('一');
         let expected = vec![
             CharCategory::new("KANJINUMERIC".to_owned(), true, true, 0),
@@ -45,7 +68,7 @@ mod tests {
 
     #[test]
     fn test_cost_matrix() {
-        let cost_matrix = decode_slice::<CostMatrix>(COST_MATRIX).unwrap();
+        let cost_matrix = CostMatrix::new(Blob::from(COST_MATRIX)).unwrap();
         let value = cost_matrix.get(0, 0);
 
         assert_eq!(value, -434);
@@ -1,3 +1,5 @@
+use std::io;
+
 use bincode::error::DecodeError;
 use thiserror::Error;
 
@@ -9,4 +11,12 @@ pub enum Error {
     DecodeError(#[from] DecodeError),
     #[error("{0}")]
     FstBuilderError(#[from] fst::Error),
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("Invalid user dictionary entry at line {line}: {message}")]
+    UserDictionaryError { line: usize, message: String },
+    #[error("Context id {0} is out of the cost matrix bounds")]
+    ContextIdOutOfBounds(u16),
+    #[error("Invalid constraint: {0}")]
+    InvalidConstraint(String),
 }
@@ -1,20 +1,35 @@
 use crate::error::Result;
 
-use fst::{raw::Output, Map};
-use mecab_types::term::TermId;
+use fst::{raw::Output, Map, MapBuilder};
+use mecab_types::blob::Blob;
 
 pub struct FstSearcher {
-    map: Map<&'static [u8]>,
+    map: Map<Blob>,
 }
 
 impl FstSearcher {
-    pub fn load(bytes: &'static [u8]) -> Result<Self> {
+    pub fn load(bytes: Blob) -> Result<Self> {
         Ok(Self {
             map: Map::new(bytes)?,
         })
     }
 
-    pub fn get_from_prefix(&self, input: &str) -> Vec<(usize, TermId)> {
+    /// Builds an in-memory FST from sorted surface forms, each mapped to its index.
+    pub fn build<'a, I>(keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut builder = MapBuilder::memory();
+
+        for (index, key) in keys.into_iter().enumerate() {
+            builder.insert(key, index as u64)?;
+        }
+
+        Self::load(Blob::from(builder.into_inner()?))
+    }
+
+    /// Returns the byte length and the surface index of every key prefixing the input.
+    pub fn get_from_prefix(&self, input: &str) -> Vec<(usize, usize)> {
         let fst = self.map.as_fst();
         let mut node = fst.root();
         let mut output = Output::zero();
@@ -29,13 +44,7 @@ impl FstSearcher {
                 len += 1;
 
                 if node.is_final() {
-                    let value = output.value();
-                    let offset = (value & 0b11111) as usize;
-                    let start = value.wrapping_shr(5) as usize;
-
-                    for id in start..(start + offset) {
-                        results.push((len, id));
-                    }
+                    results.push((len, output.value() as usize));
                 }
             } else {
                 break;
@@ -0,0 +1,321 @@
+use crate::{
+    lattice::{Lattice, Node, NodeId, END_ID, START_ID},
+    morpheme::Morpheme,
+};
+
+use std::fmt::Write;
+
+use mecab_types::{cost::CostMatrix, term::TermSource};
+
+/// Snapshot of every candidate considered for an input, mainly intended for debugging
+/// segmentations by rendering it as Graphviz DOT or JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatticeGraph {
+    pub nodes: Vec<GraphNode>,
+    pub best_path: Vec<NodeId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub id: NodeId,
+    pub kind: GraphNodeKind,
+    pub start: usize,
+    pub end: usize,
+    pub word_cost: i32,
+    /// Best cost from BOS to this node, `None` if the node is unreachable.
+    pub total_cost: Option<i32>,
+    pub best_prev: Option<NodeId>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphNodeKind {
+    Bos,
+    Eos,
+    Term {
+        source: TermSource,
+        morpheme: Box<Morpheme>,
+    },
+}
+
+/// Connection from a predecessor node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphEdge {
+    pub from: NodeId,
+    pub connection_cost: i16,
+}
+
+impl LatticeGraph {
+    pub(crate) fn new<F>(lattice: &Lattice, matrix: &CostMatrix, build_morpheme: F) -> Self
+    where
+        F: Fn(&Node) -> Morpheme,
+    {
+        let nodes = lattice
+            .nodes()
+            .iter()
+            .enumerate()
+            .map(|(id, node)| {
+                let edges = lattice
+                    .predecessors(id)
+                    .iter()
+                    .map(|&from| GraphEdge {
+                        from,
+                        connection_cost: matrix.get(lattice.nodes()[from].right_id, node.left_id),
+                    })
+                    .collect();
+                let kind = match id {
+                    START_ID => GraphNodeKind::Bos,
+                    END_ID => GraphNodeKind::Eos,
+                    _ => GraphNodeKind::Term {
+                        source: node.source,
+                        morpheme: Box::new(build_morpheme(node)),
+                    },
+                };
+
+                GraphNode {
+                    id,
+                    kind,
+                    start: node.start,
+                    end: node.end,
+                    word_cost: node.cost,
+                    total_cost: (node.total_cost != i32::MAX).then_some(node.total_cost),
+                    best_prev: node.prev_node,
+                    edges,
+                }
+            })
+            .collect();
+
+        Self {
+            nodes,
+            best_path: lattice.best_path_ids(),
+        }
+    }
+
+    pub fn is_best(&self, id: NodeId) -> bool {
+        self.best_path.contains(&id)
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        dot.push_str("digraph lattice {\n");
+        dot.push_str("  rankdir=LR;\n");
+        dot.push_str("  node [shape=box];\n");
+
+        for node in &self.nodes {
+            let total_cost = node
+                .total_cost
+                .map_or("-".to_owned(), |cost| cost.to_string());
+            let label = match &node.kind {
+                GraphNodeKind::Bos => "BOS".to_owned(),
+                GraphNodeKind::Eos => format!("EOS\ntotal: {}", total_cost),
+                GraphNodeKind::Term { morpheme, .. } => format!(
+                    "{}\n{}\ncost: {}\ntotal: {}",
+                    morpheme.text,
+                    feature_label(morpheme),
+                    node.word_cost,
+                    total_cost
+                ),
+            };
+            let style = match self.is_best(node.id) {
+                true => ", color=red, penwidth=2",
+                false => "",
+            };
+
+            let _ = writeln!(
+                dot,
+                "  n{} [label=\"{}\"{}];",
+                node.id,
+                escape(&label),
+                style
+            );
+        }
+
+        for node in &self.nodes {
+            for edge in &node.edges {
+                let style = match self.is_best_edge(edge.from, node.id) {
+                    true => ", color=red, penwidth=2",
+                    false => "",
+                };
+
+                let _ = writeln!(
+                    dot,
+                    "  n{} -> n{} [label=\"{}\"{}];",
+                    edge.from, node.id, edge.connection_cost, style
+                );
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<_> = self.nodes.iter().map(|node| self.node_json(node)).collect();
+        let best_path: Vec<_> = self.best_path.iter().map(|id| id.to_string()).collect();
+
+        format!(
+            "{{\"nodes\":[{}],\"best_path\":[{}]}}",
+            nodes.join(","),
+            best_path.join(",")
+        )
+    }
+
+    fn node_json(&self, node: &GraphNode) -> String {
+        let (kind, surface, feature) = match &node.kind {
+            GraphNodeKind::Bos => ("bos", String::new(), String::new()),
+            GraphNodeKind::Eos => ("eos", String::new(), String::new()),
+            GraphNodeKind::Term { source, morpheme } => (
+                match source {
+                    TermSource::System => "system",
+                    TermSource::User => "user",
+                    TermSource::Unknown => "unknown",
+                },
+                morpheme.text.clone(),
+                feature_label(morpheme),
+            ),
+        };
+        let edges: Vec<_> = node
+            .edges
+            .iter()
+            .map(|edge| {
+                format!(
+                    "{{\"from\":{},\"connection_cost\":{}}}",
+                    edge.from, edge.connection_cost
+                )
+            })
+            .collect();
+        let optional = |value: Option<String>| value.unwrap_or("null".to_owned());
+
+        format!(
+            "{{\"id\":{},\"kind\":\"{}\",\"surface\":\"{}\",\"feature\":\"{}\",\"start\":{},\"end\":{},\"word_cost\":{},\"total_cost\":{},\"best_prev\":{},\"best\":{},\"edges\":[{}]}}",
+            node.id,
+            kind,
+            escape(&surface),
+            escape(&feature),
+            node.start,
+            node.end,
+            node.word_cost,
+            optional(node.total_cost.map(|cost| cost.to_string())),
+            optional(node.best_prev.map(|id| id.to_string())),
+            self.is_best(node.id),
+            edges.join(",")
+        )
+    }
+
+    fn is_best_edge(&self, from: NodeId, to: NodeId) -> bool {
+        self.best_path
+            .windows(2)
+            .any(|pair| pair[0] == from && pair[1] == to)
+    }
+}
+
+fn feature_label(morpheme: &Morpheme) -> String {
+    let mut columns = vec![morpheme.part_of_speech.to_string()];
+
+    columns.extend(
+        morpheme
+            .sub_part_of_speech
+            .iter()
+            .map(|pos| pos.to_string()),
+    );
+    columns.extend(morpheme.base_form.clone());
+    columns.extend(morpheme.reading.clone());
+    columns.join(",")
+}
+
+/// Escapes a string for both JSON and DOT double-quoted strings.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for ch in value.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", ch as u32);
+            }
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mecab_types::features::Feature;
+
+    fn graph() -> LatticeGraph {
+        let feature = Feature::parse("名詞,一般,*,*,*,*,\"引用\",インヨウ,インヨー").unwrap();
+        let morpheme = Morpheme::new("\"引用\"".to_owned(), 0, 8, feature);
+        let node = |id, total_cost, best_prev, edges| GraphNode {
+            id,
+            kind: match id {
+                START_ID => GraphNodeKind::Bos,
+                END_ID => GraphNodeKind::Eos,
+                _ => GraphNodeKind::Term {
+                    source: TermSource::Unknown,
+                    morpheme: Box::new(morpheme.clone()),
+                },
+            },
+            start: 0,
+            end: 8,
+            word_cost: 100,
+            total_cost,
+            best_prev,
+            edges,
+        };
+
+        LatticeGraph {
+            nodes: vec![
+                node(START_ID, Some(0), None, vec![]),
+                node(
+                    END_ID,
+                    Some(150),
+                    Some(2),
+                    vec![GraphEdge {
+                        from: 2,
+                        connection_cost: 20,
+                    }],
+                ),
+                node(
+                    2,
+                    Some(130),
+                    Some(0),
+                    vec![GraphEdge {
+                        from: 0,
+                        connection_cost: 30,
+                    }],
+                ),
+                node(3, None, None, vec![]),
+            ],
+            best_path: vec![START_ID, 2, END_ID],
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = graph().to_dot();
+
+        assert!(dot.starts_with("digraph lattice {\n"));
+        assert!(dot.contains("  n2 [label=\"\\\"引用\\\"\\n名詞,一般,\\\"引用\\\",インヨウ\\ncost: 100\\ntotal: 130\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("  n3 [label=\"\\\"引用\\\"\\n名詞,一般,\\\"引用\\\",インヨウ\\ncost: 100\\ntotal: -\"];\n"));
+        assert!(dot.contains("  n0 -> n2 [label=\"30\", color=red, penwidth=2];\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = graph().to_json();
+
+        assert!(json.starts_with("{\"nodes\":[{\"id\":0,\"kind\":\"bos\""));
+        assert!(json.contains("{\"id\":2,\"kind\":\"unknown\",\"surface\":\"\\\"引用\\\"\",\"feature\":\"名詞,一般,\\\"引用\\\",インヨウ\",\"start\":0,\"end\":8,\"word_cost\":100,\"total_cost\":130,\"best_prev\":0,\"best\":true,\"edges\":[{\"from\":0,\"connection_cost\":30}]}"));
+        assert!(json.contains("\"total_cost\":null,\"best_prev\":null,\"best\":false"));
+        assert!(json.ends_with("\"best_path\":[0,2,1]}"));
+    }
+}
@@ -88,7 +88,7 @@ impl Inflection {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "embedded"))]
 mod tests {
     use super::Inflection;
     use crate::Tokenizer;
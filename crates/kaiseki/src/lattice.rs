@@ -1,18 +1,24 @@
-use mecab_types::{cost::CostMatrix, term::TermId};
+use std::{cmp::Reverse, collections::BinaryHeap};
 
-const START_ID: usize = 0;
-const END_ID: usize = 1;
+use mecab_types::{
+    cost::CostMatrix,
+    term::{TermId, TermSource},
+};
 
-type NodeId = usize;
+pub const START_ID: usize = 0;
+pub const END_ID: usize = 1;
+
+pub type NodeId = usize;
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Node {
     pub term_id: TermId,
-    pub unknown: bool,
+    pub source: TermSource,
     pub start: usize,
     pub end: usize,
-    pub context_id: u16,
-    pub cost: i16,
+    pub left_id: u16,
+    pub right_id: u16,
+    pub cost: i32,
     pub total_cost: i32,
     pub prev_node: Option<NodeId>,
 }
@@ -20,18 +26,20 @@ pub struct Node {
 impl Node {
     pub fn new(
         term_id: usize,
-        unknown: bool,
+        source: TermSource,
         start: usize,
         end: usize,
-        context_id: u16,
-        cost: i16,
+        left_id: u16,
+        right_id: u16,
+        cost: i32,
     ) -> Self {
         Self {
             term_id,
-            unknown,
+            source,
             start,
             end,
-            context_id,
+            left_id,
+            right_id,
             cost,
             total_cost: i32::MAX,
             prev_node: None,
@@ -77,6 +85,79 @@ impl Lattice {
     }
 
     pub fn find_path(&mut self, matrix: &CostMatrix) -> Vec<Node> {
+        self.forward(matrix);
+        self.build_path()
+    }
+
+    /// Iterates over the paths with their total cost in ascending order, using a backward A*
+    /// search on top of the forward Viterbi costs. The search only goes as far as the paths
+    /// consumed.
+    pub fn nbest_paths<'a>(&'a mut self, matrix: &'a CostMatrix) -> NBestPaths<'a> {
+        self.forward(matrix);
+
+        let mut queue = BinaryHeap::new();
+
+        if self.get_node(END_ID).total_cost != i32::MAX {
+            queue.push(Reverse((self.get_node(END_ID).total_cost, 0i32, 0)));
+        }
+
+        NBestPaths {
+            lattice: self,
+            matrix,
+            arena: vec![(END_ID, None)],
+            queue,
+        }
+    }
+
+    /// Computes the marginal probability of every node with a forward-backward pass,
+    /// the costs are scaled by `theta` before being turned into probabilities.
+    pub fn marginal_probabilities(&self, matrix: &CostMatrix, theta: f64) -> Vec<f64> {
+        let len = self.starts_at.len();
+        let mut alpha = vec![f64::NEG_INFINITY; self.nodes.len()];
+        let mut beta = vec![f64::NEG_INFINITY; self.nodes.len()];
+        let score = |prev: &Node, current: &Node| {
+            let connection_cost = matrix.get(prev.right_id, current.left_id);
+            -theta * (current.cost as f64 + connection_cost as f64)
+        };
+
+        alpha[START_ID] = 0.0;
+        beta[END_ID] = 0.0;
+
+        for i in 0..len {
+            for &current_id in &self.starts_at[i] {
+                let current = self.get_node(current_id);
+                let scores = self.ends_at[i]
+                    .iter()
+                    .map(|&prev_id| alpha[prev_id] + score(self.get_node(prev_id), current));
+
+                alpha[current_id] = log_sum_exp(scores);
+            }
+        }
+
+        for i in (0..len).rev() {
+            for &prev_id in &self.ends_at[i] {
+                let prev = self.get_node(prev_id);
+                let scores = self.starts_at[i]
+                    .iter()
+                    .map(|&next_id| beta[next_id] + score(prev, self.get_node(next_id)));
+
+                beta[prev_id] = log_sum_exp(scores);
+            }
+        }
+
+        let normalizer = alpha[END_ID];
+
+        alpha
+            .iter()
+            .zip(beta)
+            .map(|(alpha, beta)| match normalizer.is_finite() {
+                true => (alpha + beta - normalizer).exp(),
+                false => 0.0,
+            })
+            .collect()
+    }
+
+    fn forward(&mut self, matrix: &CostMatrix) {
         let len = self.starts_at.len();
 
         for i in 0..len {
@@ -87,10 +168,19 @@ impl Lattice {
                 for &prev_id in left_edges {
                     let prev_node = self.get_node(prev_id);
                     let current = self.get_node(current_id);
+
+                    // unreachable nodes and saturated words stay out of every path, even when
+                    // a negative connection cost would bring the sum back below `i32::MAX`
+                    if prev_node.total_cost == i32::MAX || current.cost == i32::MAX {
+                        continue;
+                    }
+
                     let prev_cost = prev_node.total_cost;
                     let current_cost = current.cost;
-                    let connection_cost = matrix.get(prev_node.context_id, current.context_id);
-                    let total_cost = prev_cost + current_cost as i32 + connection_cost as i32;
+                    let connection_cost = matrix.get(prev_node.right_id, current.left_id);
+                    let total_cost = prev_cost
+                        .saturating_add(current_cost)
+                        .saturating_add(connection_cost as i32);
 
                     if total_cost < current.total_cost {
                         let node = &mut self.nodes[current_id];
@@ -100,26 +190,93 @@ impl Lattice {
                 }
             }
         }
+    }
 
-        self.build_path()
+    /// Returns the last offset up to `limit` where a node ends and that no node crosses,
+    /// every path of the lattice goes through it.
+    pub fn last_convergence_point(&self, limit: usize) -> Option<usize> {
+        let len = self.ends_at.len() - 1;
+        let mut crossing = vec![0i32; len + 2];
+
+        for node in &self.nodes[END_ID + 1..] {
+            crossing[node.start + 1] += 1;
+            crossing[node.end] -= 1;
+        }
+
+        let mut count = 0;
+        let mut point = None;
+
+        for (index, delta) in crossing.iter().enumerate().take(limit.min(len) + 1) {
+            count += delta;
+
+            if index > 0 && count == 0 && self.has_node_ending_at(index) {
+                point = Some(index);
+            }
+        }
+
+        point
     }
 
     pub fn has_node_ending_at(&self, index: usize) -> bool {
         !self.ends_at[index].is_empty()
     }
 
-    fn build_path(&self) -> Vec<Node> {
-        let mut nodes = Vec::new();
+    pub fn has_node(&self, start: usize, end: usize) -> bool {
+        self.starts_at[start]
+            .iter()
+            .any(|&id| self.nodes[id].end == end)
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn predecessors(&self, id: NodeId) -> &[NodeId] {
+        match id {
+            START_ID => &[],
+            _ => &self.ends_at[self.get_node(id).start],
+        }
+    }
+
+    /// Ids of the nodes on the best path found by the last forward pass, BOS and EOS included.
+    pub fn best_path_ids(&self) -> Vec<NodeId> {
+        let mut ids = vec![END_ID];
         let mut prev_node = self.get_node(END_ID).prev_node;
 
         while let Some(prev_id) = prev_node {
-            let node = self.get_node(prev_id);
-            nodes.push(node.clone());
-            prev_node = node.prev_node;
+            ids.push(prev_id);
+            prev_node = self.get_node(prev_id).prev_node;
         }
 
-        nodes.pop();
-        nodes.reverse();
+        ids.reverse();
+
+        ids
+    }
+
+    fn build_path(&self) -> Vec<Node> {
+        let ids = self.best_path_ids();
+
+        match ids.as_slice() {
+            [START_ID, ids @ .., END_ID] => {
+                ids.iter().map(|&id| self.get_node(id).clone()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn collect_path(&self, arena: &[(NodeId, Option<usize>)], path_id: usize) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut parent = arena[path_id].1;
+
+        while let Some(path_id) = parent {
+            let (node_id, next) = arena[path_id];
+
+            if node_id != END_ID {
+                nodes.push(self.get_node(node_id).clone());
+            }
+
+            parent = next;
+        }
 
         nodes
     }
@@ -129,27 +286,224 @@ impl Lattice {
     }
 }
 
+fn log_sum_exp<I: Iterator<Item = f64>>(values: I) -> f64 {
+    let values: Vec<_> = values.collect();
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+
+    max + values
+        .iter()
+        .map(|value| (value - max).exp())
+        .sum::<f64>()
+        .ln()
+}
+
+/// Backward A* search over a lattice whose forward costs are computed.
+pub struct NBestPaths<'a> {
+    lattice: &'a Lattice,
+    matrix: &'a CostMatrix,
+    /// (node_id, parent) entries, a partial path is the chain from an entry to the end node
+    arena: Vec<(NodeId, Option<usize>)>,
+    queue: BinaryHeap<Reverse<(i32, i32, usize)>>,
+}
+
+impl Iterator for NBestPaths<'_> {
+    type Item = (Vec<Node>, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(Reverse((_, backward_cost, path_id))) = self.queue.pop() {
+            let (node_id, _) = self.arena[path_id];
+
+            if node_id == START_ID {
+                return Some((
+                    self.lattice.collect_path(&self.arena, path_id),
+                    backward_cost,
+                ));
+            }
+
+            let node = self.lattice.get_node(node_id);
+
+            for &prev_id in &self.lattice.ends_at[node.start] {
+                let prev_node = self.lattice.get_node(prev_id);
+
+                if prev_node.total_cost == i32::MAX {
+                    continue;
+                }
+
+                let connection_cost = self.matrix.get(prev_node.right_id, node.left_id);
+                let backward_cost = backward_cost
+                    .saturating_add(node.cost)
+                    .saturating_add(connection_cost as i32);
+                let priority = prev_node.total_cost.saturating_add(backward_cost);
+
+                self.arena.push((prev_id, Some(path_id)));
+                self.queue
+                    .push(Reverse((priority, backward_cost, self.arena.len() - 1)));
+            }
+        }
+
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    use mecab_types::{blob::Blob, utils::FlatMatrix};
+
+    fn encode(matrix: &FlatMatrix<i16>) -> CostMatrix {
+        let mut buffer = Vec::new();
+
+        CostMatrix::encode(matrix, &mut buffer).unwrap();
+        CostMatrix::new(Blob::from(buffer)).unwrap()
+    }
+
+    /// Cost matrix whose connection costs are all zero.
+    fn matrix(rows: usize, cols: usize) -> CostMatrix {
+        encode(&FlatMatrix::new(rows, cols))
+    }
+
+    /// Lattice of 東京都 with two segmentations of 東京 and two homographs of 都.
+    fn tokyo_lattice() -> Lattice {
+        let mut lattice = Lattice::new(9);
+
+        lattice.add_node(Node::new(1, TermSource::System, 0, 3, 1, 1, 300)); // 東
+        lattice.add_node(Node::new(2, TermSource::System, 0, 6, 1, 1, 400)); // 東京
+        lattice.add_node(Node::new(3, TermSource::System, 3, 6, 1, 1, 200)); // 京
+        lattice.add_node(Node::new(4, TermSource::System, 6, 9, 2, 2, 100)); // 都
+        lattice.add_node(Node::new(5, TermSource::System, 6, 9, 2, 2, 150)); // 都
+
+        lattice
+    }
+
+    #[test]
+    fn test_nbest_paths() {
+        let mut lattice = tokyo_lattice();
+        let cost_matrix = matrix(3, 3);
+
+        let paths: Vec<_> = lattice.nbest_paths(&cost_matrix).take(3).collect();
+        let term_ids: Vec<Vec<_>> = paths
+            .iter()
+            .map(|(nodes, _)| nodes.iter().map(|node| node.term_id).collect())
+            .collect();
+        let costs: Vec<_> = paths.iter().map(|(_, cost)| *cost).collect();
+
+        assert_eq!(vec![vec![2, 4], vec![2, 5], vec![1, 3, 4]], term_ids);
+        assert_eq!(vec![500, 550, 600], costs);
+        assert_eq!(
+            2,
+            lattice.nbest_paths(&cost_matrix).nth(1).unwrap().0[0].term_id
+        );
+        assert_eq!(4, lattice.nbest_paths(&cost_matrix).count());
+    }
+
+    #[test]
+    fn test_large_costs() {
+        let mut lattice = Lattice::new(12);
+        let mut costs = FlatMatrix::new(2, 2);
+
+        costs.set(0, 1, -100); // BOS followed by left_id 1
+        costs.set(1, 0, -100); // right_id 1 followed by EOS
+
+        let cost_matrix = encode(&costs);
+
+        // penalized words keep costs beyond the range of the dictionary costs
+        lattice.add_node(Node::new(1, TermSource::System, 0, 12, 1, 1, 40000));
+        lattice.add_node(Node::new(2, TermSource::System, 0, 6, 1, 1, 20000));
+        lattice.add_node(Node::new(3, TermSource::System, 6, 12, 1, 1, 19000));
+        lattice.add_node(Node::new(4, TermSource::System, 0, 12, 1, 1, i32::MAX));
+
+        let path = lattice.find_path(&cost_matrix);
+
+        assert_eq!(
+            vec![2, 3],
+            path.iter().map(|node| node.term_id).collect::<Vec<_>>()
+        );
+        // a saturated cost keeps the word unreachable despite the negative connection costs
+        assert_eq!(2, lattice.nbest_paths(&cost_matrix).count());
+    }
+
+    #[test]
+    fn test_marginal_probabilities() {
+        let lattice = tokyo_lattice();
+        let cost_matrix = matrix(3, 3);
+
+        let probabilities = lattice.marginal_probabilities(&cost_matrix, 0.01);
+        let weights = [500.0, 550.0, 600.0, 650.0].map(|cost: f64| (-0.01 * cost).exp());
+        let total: f64 = weights.iter().sum();
+        let expected = (weights[0] + weights[1]) / total; // 東京
+
+        assert!((probabilities[3] - expected).abs() < 1e-9);
+        assert!((probabilities[2] + probabilities[3] - 1.0).abs() < 1e-9);
+        assert!((probabilities[2] - probabilities[4]).abs() < 1e-9);
+        assert!((probabilities[START_ID] - 1.0).abs() < 1e-9);
+        assert!((probabilities[END_ID] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_left_right_ids() {
+        let mut lattice = Lattice::new(6);
+        let mut costs = FlatMatrix::new(3, 3);
+
+        costs.set(2, 1, 1000); // right_id 2 followed by left_id 1
+
+        let cost_matrix = encode(&costs);
+
+        lattice.add_node(Node::new(1, TermSource::System, 0, 3, 1, 2, 100));
+        lattice.add_node(Node::new(2, TermSource::System, 0, 3, 2, 1, 200));
+        lattice.add_node(Node::new(3, TermSource::System, 3, 6, 1, 1, 100));
+
+        let term_ids: Vec<_> = lattice
+            .find_path(&cost_matrix)
+            .iter()
+            .map(|node| node.term_id)
+            .collect();
+
+        assert_eq!(vec![2, 3], term_ids);
+    }
+
+    #[test]
+    fn test_last_convergence_point() {
+        let mut lattice = Lattice::new(12);
+
+        lattice.add_node(Node::new(1, TermSource::System, 0, 3, 1, 1, 100));
+        lattice.add_node(Node::new(2, TermSource::System, 0, 6, 1, 1, 100));
+        lattice.add_node(Node::new(3, TermSource::System, 3, 6, 1, 1, 100));
+        lattice.add_node(Node::new(4, TermSource::System, 6, 9, 1, 1, 100));
+        lattice.add_node(Node::new(5, TermSource::System, 6, 12, 1, 1, 100));
+        lattice.add_node(Node::new(6, TermSource::System, 9, 12, 1, 1, 100));
+
+        assert_eq!(Some(6), lattice.last_convergence_point(9));
+        assert_eq!(Some(12), lattice.last_convergence_point(12));
+        assert_eq!(None, lattice.last_convergence_point(5));
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod embedded_tests {
     use crate::consts::COST_MATRIX;
 
     use super::*;
 
-    use mecab_types::bincode::decode_slice;
+    use mecab_types::blob::Blob;
 
     #[test]
     fn test_find_path() {
         let mut lattice = Lattice::new(18);
-        let cost_matrix = decode_slice::<CostMatrix>(COST_MATRIX).unwrap();
-
-        lattice.add_node(Node::new(1, false, 0, 3, 5, 6245)); // 東
-        lattice.add_node(Node::new(2, false, 0, 6, 3, 3003)); // 東京
-        lattice.add_node(Node::new(3, false, 3, 6, 3, 10791)); // 京
-        lattice.add_node(Node::new(4, false, 6, 9, 5, 7595)); // 都
-        lattice.add_node(Node::new(5, false, 6, 9, 6, 9428)); // 都
-        lattice.add_node(Node::new(6, false, 9, 12, 1, 4303)); // に
-        lattice.add_node(Node::new(7, false, 9, 12, 2, 11880)); // に
-        lattice.add_node(Node::new(8, false, 12, 18, 4, 7048)); // 住む
+        let cost_matrix = CostMatrix::new(Blob::from(COST_MATRIX)).unwrap();
+
+        lattice.add_node(Node::new(1, TermSource::System, 0, 3, 5, 5, 6245)); // 東
+        lattice.add_node(Node::new(2, TermSource::System, 0, 6, 3, 3, 3003)); // 東京
+        lattice.add_node(Node::new(3, TermSource::System, 3, 6, 3, 3, 10791)); // 京
+        lattice.add_node(Node::new(4, TermSource::System, 6, 9, 5, 5, 7595)); // 都
+        lattice.add_node(Node::new(5, TermSource::System, 6, 9, 6, 6, 9428)); // 都
+        lattice.add_node(Node::new(6, TermSource::System, 9, 12, 1, 1, 4303)); // に
+        lattice.add_node(Node::new(7, TermSource::System, 9, 12, 2, 2, 11880)); // に
+        lattice.add_node(Node::new(8, TermSource::System, 12, 18, 4, 4, 7048)); // 住む
 
         let nodes = lattice.find_path(&cost_matrix);
 
@@ -1,24 +1,44 @@
+pub mod constraint;
 pub mod error;
+pub mod graph;
 pub mod inflection;
+pub mod mode;
 pub mod morpheme;
+pub mod normalize;
+pub mod offset;
+pub mod sentence;
+pub mod token;
 pub mod tokenizer;
+pub mod user_dict;
 pub mod word;
 
 mod consts;
 mod fst;
 mod lattice;
+mod stream;
 
+pub use constraint::Constraints;
+pub use graph::LatticeGraph;
 pub use inflection::Inflection;
+pub use mode::Mode;
 pub use morpheme::Morpheme;
+pub use normalize::Normalizer;
+pub use offset::Offsets;
+pub use sentence::Sentence;
+pub use token::Token;
 pub use tokenizer::Tokenizer;
+pub use user_dict::UserDictionary;
 pub use word::Word;
 
+#[cfg(feature = "embedded")]
 use error::Result;
 
+#[cfg(feature = "embedded")]
 pub fn tokenize(input: &str) -> Result<Vec<Morpheme>> {
     Ok(Tokenizer::new()?.tokenize(input))
 }
 
+#[cfg(feature = "embedded")]
 pub fn tokenize_word(input: &str) -> Result<Vec<Word>> {
     Ok(Tokenizer::new()?.tokenize_word(input))
 }
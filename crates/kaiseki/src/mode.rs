@@ -0,0 +1,78 @@
+/// Segmentation mode of the tokenizer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    /// Penalizes long words so that compounds are split into their parts, which is better
+    /// suited for full-text search.
+    Search(Penalty),
+    /// Search mode where unknown words are further split into single characters.
+    Extended(Penalty),
+}
+
+/// Cost added to the words longer than a threshold, proportionally to the extra length.
+/// Lengths are counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Penalty {
+    /// Length above which a word only made of kanji is penalized.
+    pub kanji_length: usize,
+    pub kanji_penalty: i32,
+    /// Length above which any other word is penalized.
+    pub other_length: usize,
+    pub other_penalty: i32,
+}
+
+impl Default for Penalty {
+    fn default() -> Self {
+        Self {
+            kanji_length: 2,
+            kanji_penalty: 3000,
+            other_length: 7,
+            other_penalty: 1700,
+        }
+    }
+}
+
+impl Mode {
+    pub fn penalty(&self) -> Option<&Penalty> {
+        match self {
+            Mode::Normal => None,
+            Mode::Search(penalty) | Mode::Extended(penalty) => Some(penalty),
+        }
+    }
+}
+
+impl Penalty {
+    pub fn get(&self, length: usize, is_kanji: bool) -> i32 {
+        if is_kanji && length > self.kanji_length {
+            penalty_of(length - self.kanji_length, self.kanji_penalty)
+        } else if length > self.other_length {
+            penalty_of(length - self.other_length, self.other_penalty)
+        } else {
+            0
+        }
+    }
+}
+
+fn penalty_of(excess: usize, penalty: i32) -> i32 {
+    i32::try_from(excess)
+        .unwrap_or(i32::MAX)
+        .saturating_mul(penalty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_penalty() {
+        let penalty = Penalty::default();
+
+        assert_eq!(0, penalty.get(2, true));
+        assert_eq!(12000, penalty.get(6, true));
+        assert_eq!(0, penalty.get(6, false));
+        assert_eq!(3400, penalty.get(9, false));
+        assert_eq!(None, Mode::Normal.penalty());
+        assert_eq!(i32::MAX, penalty.get(usize::MAX, true));
+    }
+}
@@ -1,7 +1,10 @@
+use crate::offset::Offsets;
+
 use mecab_types::{
     conjugation::{ConjugationForm, ConjugationType},
     features::Feature,
     pos::{PartOfSpeech, SubPartOfSpeech},
+    unidic::UnidicFeature,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -15,10 +18,33 @@ pub struct Morpheme {
     pub conjugation_form: Option<ConjugationForm>,
     pub base_form: Option<String>,
     pub reading: Option<String>,
+    pub pronunciation: Option<String>,
+    /// Marginal probability of the morpheme, only computed on demand.
+    pub probability: Option<f64>,
+    /// Offsets in chars and UTF-16 code units, computed when enabled with
+    /// [`Tokenizer::with_offsets`](crate::Tokenizer::with_offsets).
+    pub offsets: Option<Offsets>,
+    /// Full UniDic feature when the tokenizer uses a UniDic dictionary.
+    pub unidic: Option<Box<UnidicFeature>>,
+    pub(crate) raw_feature: String,
 }
 
 impl Morpheme {
+    /// Creates a morpheme from typed features, the raw features are formatted from them using
+    /// the ipadic layout.
     pub fn new(text: String, start: usize, end: usize, feature: Feature) -> Self {
+        let raw_feature = feature.to_columns();
+
+        Self::with_raw_feature(text, start, end, feature, raw_feature)
+    }
+
+    pub(crate) fn with_raw_feature(
+        text: String,
+        start: usize,
+        end: usize,
+        feature: Feature,
+        raw_feature: String,
+    ) -> Self {
         Self {
             text,
             start,
@@ -29,9 +55,24 @@ impl Morpheme {
             conjugation_form: feature.conjugation_form,
             base_form: feature.base_form,
             reading: feature.reading,
+            pronunciation: feature.pronunciation,
+            probability: None,
+            offsets: None,
+            unidic: None,
+            raw_feature,
         }
     }
 
+    /// Feature columns as stored in the dictionary, including the ones without typed field.
+    pub fn raw_features(&self) -> &str {
+        &self.raw_feature
+    }
+
+    /// Returns the feature column at a 0-based index, `*` columns included.
+    pub fn feature(&self, index: usize) -> Option<&str> {
+        self.raw_feature.split(',').nth(index)
+    }
+
     pub fn has_sub_pos(&self, pos: &SubPartOfSpeech) -> bool {
         self.sub_part_of_speech.contains(pos)
     }
@@ -0,0 +1,282 @@
+const HALFWIDTH_KANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+const WAVE_DASHES: [char; 6] = [
+    '\u{301C}', '\u{3030}', '\u{223C}', '\u{223E}', '\u{2053}', '\u{FF5E}',
+];
+
+const PROLONGED_SOUND_MARKS: [char; 9] = [
+    '\u{2014}', '\u{2015}', '\u{2212}', '\u{2500}', '\u{2501}', '\u{FE63}', '\u{FF0D}', '\u{FF70}',
+    '\u{30FC}',
+];
+
+const COMPATIBILITY: [(char, &str); 28] = [
+    ('\u{00A0}', " "),
+    ('№', "No"),
+    ('℡', "TEL"),
+    ('™', "TM"),
+    ('㈱', "(株)"),
+    ('㈲', "(有)"),
+    ('㈹', "(代)"),
+    ('㍿', "株式会社"),
+    ('㍾', "明治"),
+    ('㍽', "大正"),
+    ('㍼', "昭和"),
+    ('㍻', "平成"),
+    ('㋿', "令和"),
+    ('㌃', "アール"),
+    ('㌍', "カロリー"),
+    ('㌔', "キロ"),
+    ('㌘', "グラム"),
+    ('㌢', "センチ"),
+    ('㌦', "ドル"),
+    ('㌧', "トン"),
+    ('㌫', "パーセント"),
+    ('㌶', "ヘクタール"),
+    ('㍉', "ミリ"),
+    ('㍍', "メートル"),
+    ('㍑', "リットル"),
+    ('㎏', "kg"),
+    ('㎝', "cm"),
+    ('㎞', "km"),
+];
+
+const ROMAN_NUMERALS: [&str; 12] = [
+    "I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X", "XI", "XII",
+];
+
+/// Character normalization applied to the input before it is tokenized, every rule is enabled
+/// by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalizer {
+    /// Folds full-width ASCII and the ideographic space into ASCII.
+    pub fullwidth_ascii: bool,
+    /// Folds half-width katakana into full-width katakana, combining the voiced sound marks.
+    pub halfwidth_kana: bool,
+    /// Decomposes common compatibility characters such as ㈱, ① or ㌔.
+    pub compatibility: bool,
+    /// Unifies the dashes following a kana into the prolonged sound mark ー.
+    pub prolonged_sound_mark: bool,
+    /// Unifies the wave dash variants into 〜.
+    pub wave_dash: bool,
+}
+
+/// Normalized text keeping track of the original span of each normalized character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalized {
+    pub text: String,
+    segments: Vec<Segment>,
+    original_len: usize,
+}
+
+/// Part of the normalized text starting at `start`, coming from `original_start..original_end`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    start: usize,
+    original_start: usize,
+    original_end: usize,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            fullwidth_ascii: true,
+            halfwidth_kana: true,
+            compatibility: true,
+            prolonged_sound_mark: true,
+            wave_dash: true,
+        }
+    }
+}
+
+impl Normalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn normalize(&self, input: &str) -> Normalized {
+        let mut text = String::with_capacity(input.len());
+        let mut segments = Vec::with_capacity(input.len());
+        let mut chars = input.char_indices().peekable();
+
+        while let Some((original_start, ch)) = chars.next() {
+            let start = text.len();
+            let mut original_end = original_start + ch.len_utf8();
+
+            if self.halfwidth_kana && is_halfwidth_kana(ch) {
+                let kana = fold_halfwidth_kana(ch);
+                let mark = chars.peek().and_then(|&(_, next)| combine_mark(kana, next));
+
+                match mark {
+                    Some(combined) => {
+                        original_end += chars.next().map_or(0, |(_, next)| next.len_utf8());
+                        text.push(combined);
+                    }
+                    None => text.push(kana),
+                }
+            } else if self.wave_dash && WAVE_DASHES.contains(&ch) {
+                text.push('〜');
+            } else if self.prolonged_sound_mark
+                && PROLONGED_SOUND_MARKS.contains(&ch)
+                && text.chars().next_back().is_some_and(is_kana)
+            {
+                text.push('ー');
+            } else if self.fullwidth_ascii && is_fullwidth_ascii(ch) {
+                text.push(fold_fullwidth_ascii(ch));
+            } else if let Some(decomposed) = self.decompose(ch) {
+                text.push_str(&decomposed);
+            } else {
+                text.push(ch);
+            }
+
+            segments.push(Segment {
+                start,
+                original_start,
+                original_end,
+            });
+        }
+
+        Normalized {
+            text,
+            segments,
+            original_len: input.len(),
+        }
+    }
+
+    fn decompose(&self, ch: char) -> Option<String> {
+        if !self.compatibility {
+            return None;
+        }
+
+        let code = ch as u32;
+
+        match code {
+            0x2460..=0x2473 => Some((code - 0x2460 + 1).to_string()),
+            0x2160..=0x216B => Some(ROMAN_NUMERALS[(code - 0x2160) as usize].to_owned()),
+            0x2170..=0x217B => Some(ROMAN_NUMERALS[(code - 0x2170) as usize].to_lowercase()),
+            _ => COMPATIBILITY
+                .iter()
+                .find(|(value, _)| *value == ch)
+                .map(|(_, decomposed)| (*decomposed).to_owned()),
+        }
+    }
+}
+
+impl Normalized {
+    /// Maps a start offset of the normalized text to the original input.
+    pub fn original_start(&self, index: usize) -> usize {
+        if index >= self.text.len() {
+            return self.original_len;
+        }
+
+        let position = self
+            .segments
+            .partition_point(|segment| segment.start <= index);
+
+        self.segments[position - 1].original_start
+    }
+
+    /// Maps an end offset of the normalized text to the original input.
+    pub fn original_end(&self, index: usize) -> usize {
+        let position = self
+            .segments
+            .partition_point(|segment| segment.start < index);
+
+        position
+            .checked_sub(1)
+            .map_or(0, |position| self.segments[position].original_end)
+    }
+
+    /// Maps an offset of the original input, `None` if it falls inside a normalized character.
+    pub fn normalized_offset(&self, original: usize) -> Option<usize> {
+        if original == self.original_len {
+            return Some(self.text.len());
+        }
+
+        self.segments
+            .binary_search_by_key(&original, |segment| segment.original_start)
+            .ok()
+            .map(|position| self.segments[position].start)
+    }
+}
+
+fn is_fullwidth_ascii(ch: char) -> bool {
+    matches!(ch, '\u{FF01}'..='\u{FF5E}' | '\u{3000}')
+}
+
+fn fold_fullwidth_ascii(ch: char) -> char {
+    match ch {
+        '\u{3000}' => ' ',
+        _ => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+    }
+}
+
+fn is_halfwidth_kana(ch: char) -> bool {
+    matches!(ch, '\u{FF61}'..='\u{FF9F}')
+}
+
+fn fold_halfwidth_kana(ch: char) -> char {
+    let index = (ch as u32 - 0xFF61) as usize;
+
+    HALFWIDTH_KANA.chars().nth(index).unwrap_or(ch)
+}
+
+/// Combines a full-width katakana with a following half-width (semi-)voiced sound mark.
+fn combine_mark(kana: char, mark: char) -> Option<char> {
+    let offset = match mark {
+        'ﾞ' if kana == 'ウ' => return Some('ヴ'),
+        'ﾞ' if "カキクケコサシスセソタチツテトハヒフヘホ".contains(kana) => 1,
+        'ﾟ' if "ハヒフヘホ".contains(kana) => 2,
+        _ => return None,
+    };
+
+    char::from_u32(kana as u32 + offset)
+}
+
+fn is_kana(ch: char) -> bool {
+    matches!(ch, '\u{3041}'..='\u{309F}' | '\u{30A1}'..='\u{30FF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = Normalizer::new();
+
+        assert_eq!(63, HALFWIDTH_KANA.chars().count());
+        assert_eq!("ABC 123", normalizer.normalize("ＡＢＣ　１２３").text);
+        assert_eq!("カタカナ", normalizer.normalize("ｶﾀｶﾅ").text);
+        assert_eq!("ガギパヴボ゛", normalizer.normalize("ｶﾞｷﾞﾊﾟｳﾞﾎﾞﾞ").text);
+        assert_eq!("ラーメン〜", normalizer.normalize("ラ―メン～").text);
+        assert_eq!("A—B", normalizer.normalize("A—B").text);
+        assert_eq!("(株)キロ12", normalizer.normalize("㈱㌔⑫").text);
+    }
+
+    #[test]
+    fn test_offset_mapping() {
+        let input = "ｶﾞｽ㌔ＡＢ";
+        let normalized = Normalizer::new().normalize(input);
+
+        assert_eq!("ガスキロAB", normalized.text);
+        assert_eq!(
+            (0, 6),
+            (normalized.original_start(0), normalized.original_end(3))
+        );
+        assert_eq!(
+            (6, 9),
+            (normalized.original_start(3), normalized.original_end(6))
+        );
+        assert_eq!(
+            (9, 12),
+            (normalized.original_start(9), normalized.original_end(12))
+        );
+        assert_eq!(
+            (12, 18),
+            (normalized.original_start(12), normalized.original_end(14))
+        );
+        assert_eq!(Some(6), normalized.normalized_offset(9));
+        assert_eq!(None, normalized.normalized_offset(3));
+        assert_eq!(Some(14), normalized.normalized_offset(input.len()));
+    }
+}
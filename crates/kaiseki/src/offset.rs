@@ -0,0 +1,147 @@
+/// Offsets of a span in code points and in UTF-16 code units.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Offsets {
+    pub char_start: usize,
+    pub char_end: usize,
+    pub utf16_start: usize,
+    pub utf16_end: usize,
+}
+
+impl Offsets {
+    /// Makes relative offsets relative to the start of `base`.
+    pub(crate) fn shift(&mut self, base: &Offsets) {
+        self.char_start += base.char_start;
+        self.char_end += base.char_start;
+        self.utf16_start += base.utf16_start;
+        self.utf16_end += base.utf16_start;
+    }
+}
+
+/// Converts byte offsets of a text, only scanning forward as long as the offsets are increasing.
+pub(crate) struct OffsetCounter<'a> {
+    text: &'a str,
+    byte: usize,
+    chars: usize,
+    utf16: usize,
+}
+
+impl<'a> OffsetCounter<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            byte: 0,
+            chars: 0,
+            utf16: 0,
+        }
+    }
+
+    pub fn offsets(&mut self, start: usize, end: usize) -> Offsets {
+        let (char_start, utf16_start) = self.advance_to(start);
+        let (char_end, utf16_end) = self.advance_to(end);
+
+        Offsets {
+            char_start,
+            char_end,
+            utf16_start,
+            utf16_end,
+        }
+    }
+
+    fn advance_to(&mut self, byte: usize) -> (usize, usize) {
+        if byte < self.byte {
+            *self = Self::new(self.text);
+        }
+
+        for ch in self.text[self.byte..byte].chars() {
+            self.chars += 1;
+            self.utf16 += ch.len_utf16();
+        }
+
+        self.byte = byte;
+
+        (self.chars, self.utf16)
+    }
+}
+
+/// Converts the byte offsets of spans that may overlap or be out of order, the text is only
+/// scanned once over the sorted offsets.
+pub(crate) fn span_offsets(text: &str, spans: &[(usize, usize)]) -> Vec<Offsets> {
+    let mut bytes: Vec<_> = spans
+        .iter()
+        .flat_map(|&(start, end)| [start, end])
+        .collect();
+
+    bytes.sort_unstable();
+    bytes.dedup();
+
+    let mut counter = OffsetCounter::new(text);
+    let converted: Vec<_> = bytes.iter().map(|&byte| counter.advance_to(byte)).collect();
+    let convert = |byte: usize| converted[bytes.binary_search(&byte).unwrap()];
+
+    spans
+        .iter()
+        .map(|&(start, end)| {
+            let (char_start, utf16_start) = convert(start);
+            let (char_end, utf16_end) = convert(end);
+
+            Offsets {
+                char_start,
+                char_end,
+                utf16_start,
+                utf16_end,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets() {
+        let text = "a𠮷野家😀です";
+        let mut counter = OffsetCounter::new(text);
+
+        assert_eq!(
+            Offsets {
+                char_start: 1,
+                char_end: 3,
+                utf16_start: 1,
+                utf16_end: 4,
+            },
+            counter.offsets(1, 8)
+        );
+        assert_eq!(
+            Offsets {
+                char_start: 5,
+                char_end: 7,
+                utf16_start: 7,
+                utf16_end: 9,
+            },
+            counter.offsets(15, text.len())
+        );
+        assert_eq!(
+            Offsets {
+                char_start: 0,
+                char_end: 1,
+                utf16_start: 0,
+                utf16_end: 1,
+            },
+            counter.offsets(0, 1)
+        );
+    }
+
+    #[test]
+    fn test_span_offsets() {
+        let text = "a𠮷野家😀です";
+        let spans = [(15, text.len()), (1, 8), (0, 11), (1, 5)];
+        let mut counter = OffsetCounter::new(text);
+        let expected: Vec<_> = spans
+            .iter()
+            .map(|&(start, end)| counter.offsets(start, end))
+            .collect();
+
+        assert_eq!(expected, span_offsets(text, &spans));
+    }
+}
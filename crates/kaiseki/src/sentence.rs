@@ -0,0 +1,171 @@
+const TERMINATORS: [char; 3] = ['。', '！', '？'];
+
+const OPENING_BRACKETS: [char; 3] = ['「', '『', '（'];
+
+const CLOSING_BRACKETS: [char; 3] = ['」', '』', '）'];
+
+/// Number of characters after which an unclosed bracket stops preventing sentence splits.
+const MAX_BRACKET_LEN: usize = 256;
+
+/// Sentence of a document, `start` and `end` are byte offsets in the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sentence<'a> {
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Iterator over the sentences of a document.
+///
+/// Sentences end after a run of `。`, `！` or `？` outside of brackets and at line breaks,
+/// surrounding whitespaces are trimmed and blank sentences are skipped. Brackets left unclosed at
+/// the end of a line or after 256 characters are ignored.
+#[derive(Debug, Clone)]
+pub struct Sentences<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Sentences<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self { text, position: 0 }
+    }
+
+    /// Returns the end of the next sentence and the offset where the following one starts.
+    fn find_end(&self) -> (usize, usize) {
+        let mut depth = 0usize;
+        let mut opened_at = 0;
+        // end of the first terminators inside brackets, used when the brackets are not closed
+        let mut fallback = None;
+        let mut chars = self.text[self.position..]
+            .char_indices()
+            .enumerate()
+            .peekable();
+
+        while let Some((count, (index, ch))) = chars.next() {
+            let index = self.position + index;
+
+            if depth > 0 && count - opened_at > MAX_BRACKET_LEN {
+                if let Some(end) = fallback {
+                    return (end, end);
+                }
+
+                depth = 0;
+            }
+
+            match ch {
+                '\n' => match fallback.filter(|_| depth > 0) {
+                    Some(end) => return (end, end),
+                    None => return (index, index + 1),
+                },
+                _ if OPENING_BRACKETS.contains(&ch) => {
+                    if depth == 0 {
+                        opened_at = count;
+                    }
+
+                    depth += 1;
+                }
+                _ if CLOSING_BRACKETS.contains(&ch) => {
+                    depth = depth.saturating_sub(1);
+
+                    if depth == 0 {
+                        fallback = None;
+                    }
+                }
+                _ if TERMINATORS.contains(&ch) => {
+                    let mut end = index + ch.len_utf8();
+
+                    while let Some(&(_, (next_index, next))) = chars.peek() {
+                        if !TERMINATORS.contains(&next) {
+                            break;
+                        }
+
+                        end = self.position + next_index + next.len_utf8();
+                        chars.next();
+                    }
+
+                    if depth == 0 {
+                        return (end, end);
+                    }
+
+                    fallback.get_or_insert(end);
+                }
+                _ => {}
+            }
+        }
+
+        (self.text.len(), self.text.len())
+    }
+}
+
+impl<'a> Iterator for Sentences<'a> {
+    type Item = Sentence<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.text.len() {
+            let (end, next) = self.find_end();
+            let raw = &self.text[self.position..end];
+            let text = raw.trim();
+            let start = self.position + (raw.len() - raw.trim_start().len());
+
+            self.position = next;
+
+            if !text.is_empty() {
+                return Some(Sentence {
+                    text,
+                    start,
+                    end: start + text.len(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentences() {
+        let text = "「行く？」と聞いた。はい！？\r\n\n（笑。）次の文　";
+        let sentences: Vec<_> = Sentences::new(text).map(|sentence| sentence.text).collect();
+
+        assert_eq!(
+            vec!["「行く？」と聞いた。", "はい！？", "（笑。）次の文"],
+            sentences
+        );
+
+        let sentence = Sentences::new(text).nth(2).unwrap();
+
+        assert_eq!(sentence.text, &text[sentence.start..sentence.end]);
+        assert_eq!(0, Sentences::new(" \n　").count());
+    }
+
+    #[test]
+    fn test_unbalanced_brackets() {
+        let sentences = |text| {
+            Sentences::new(text)
+                .map(|sentence| sentence.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["「あれ。これ。"], sentences("「あれ。これ。"));
+        assert_eq!(
+            vec!["「あれ。", "これ。", "それ。"],
+            sentences("「あれ。これ。\nそれ。")
+        );
+        assert_eq!(vec!["「あ。", "い", "う。"], sentences("「あ。い\nう。"));
+        assert_eq!(vec!["「あ」。", "い。"], sentences("「あ」。い。"));
+
+        let text = format!("（{}。い。", "あ".repeat(MAX_BRACKET_LEN));
+
+        assert_eq!(2, sentences(&text).len());
+        assert_eq!("い。", sentences(&text)[1]);
+
+        let text = format!("（あ。{}。", "い".repeat(MAX_BRACKET_LEN));
+
+        assert_eq!(vec!["（あ。", &text[9..]], sentences(&text));
+    }
+}
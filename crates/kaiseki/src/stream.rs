@@ -0,0 +1,281 @@
+use crate::{
+    morpheme::Morpheme,
+    offset::{OffsetCounter, Offsets},
+    sentence::Sentences,
+    tokenizer::Tokenizer,
+};
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead},
+};
+
+/// Maximum number of bytes taken from a reader at once.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// Text length above which a lattice is cut without waiting for a sentence end.
+const MAX_BUFFER_LEN: usize = 64 * 1024;
+
+/// Text kept after a forced cut, longer than any dictionary entry so that the nodes before
+/// the cut don't depend on the text that hasn't been read yet.
+const CUT_MARGIN: usize = 1024;
+
+/// Incremental tokenization of a text read in chunks, the text is tokenized up to the last
+/// complete sentence each time a chunk is read.
+pub(crate) struct MorphemeStream<'a, I> {
+    tokenizer: &'a Tokenizer,
+    chunks: I,
+    buffer: String,
+    /// Offsets of the start of the buffer in the whole text.
+    consumed: Offsets,
+    consumed_bytes: usize,
+    pending: VecDeque<Morpheme>,
+    is_finished: bool,
+}
+
+impl<'a, I> MorphemeStream<'a, I> {
+    pub fn new(tokenizer: &'a Tokenizer, chunks: I) -> Self {
+        Self {
+            tokenizer,
+            chunks,
+            buffer: String::new(),
+            consumed: Offsets::default(),
+            consumed_bytes: 0,
+            pending: VecDeque::new(),
+            is_finished: false,
+        }
+    }
+
+    fn find_cut(&self) -> Option<usize> {
+        if self.is_finished {
+            return Some(self.buffer.len()).filter(|&len| len > 0);
+        }
+
+        if let Some(cut) = find_sentence_cut(&self.buffer) {
+            return Some(cut);
+        }
+
+        if self.buffer.len() < MAX_BUFFER_LEN + CUT_MARGIN {
+            return None;
+        }
+
+        Some(piece_end(self.tokenizer, &self.buffer))
+    }
+
+    fn flush(&mut self, cut: usize) {
+        let text = &self.buffer[..cut];
+        let mut counter = OffsetCounter::new(text);
+
+        for sentence in Sentences::new(text) {
+            let mut start = sentence.start;
+
+            while start < sentence.end {
+                let end = start + piece_end(self.tokenizer, &text[start..sentence.end]);
+                let base = counter.offsets(start, end);
+
+                for mut morpheme in self.tokenizer.tokenize(&text[start..end]) {
+                    morpheme.start += self.consumed_bytes + start;
+                    morpheme.end += self.consumed_bytes + start;
+
+                    if let Some(offsets) = &mut morpheme.offsets {
+                        offsets.shift(&base);
+                        offsets.shift(&self.consumed);
+                    }
+
+                    self.pending.push_back(morpheme);
+                }
+
+                start = end;
+            }
+        }
+
+        for ch in text.chars() {
+            self.consumed.char_start += 1;
+            self.consumed.utf16_start += ch.len_utf16();
+        }
+
+        self.consumed_bytes += cut;
+        self.buffer.drain(..cut);
+    }
+}
+
+impl<I, S, E> Iterator for MorphemeStream<'_, I>
+where
+    I: Iterator<Item = Result<S, E>>,
+    S: AsRef<str>,
+{
+    type Item = Result<Morpheme, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(morpheme) = self.pending.pop_front() {
+                return Some(Ok(morpheme));
+            }
+
+            if self.is_finished {
+                return None;
+            }
+
+            match self.chunks.next() {
+                Some(Ok(chunk)) => self.buffer.push_str(chunk.as_ref()),
+                Some(Err(err)) => {
+                    self.is_finished = true;
+                    return Some(Err(err));
+                }
+                None => self.is_finished = true,
+            }
+
+            while let Some(cut) = self.find_cut() {
+                self.flush(cut);
+            }
+        }
+    }
+}
+
+/// Chunks of text read from a reader, a character split between two reads is carried over to
+/// the next chunk.
+pub(crate) struct ReaderChunks<R> {
+    reader: R,
+    carry: Vec<u8>,
+}
+
+impl<R> ReaderChunks<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            carry: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReaderChunks<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if buf.is_empty() {
+                if self.carry.is_empty() {
+                    return None;
+                }
+
+                self.carry.clear();
+
+                return Some(Err(invalid_utf8()));
+            }
+
+            let len = buf.len().min(READ_CHUNK_LEN);
+            let mut bytes = std::mem::take(&mut self.carry);
+
+            bytes.extend_from_slice(&buf[..len]);
+            self.reader.consume(len);
+
+            let valid = match std::str::from_utf8(&bytes) {
+                Ok(_) => bytes.len(),
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(_) => return Some(Err(invalid_utf8())),
+            };
+
+            self.carry = bytes.split_off(valid);
+
+            if !bytes.is_empty() {
+                return Some(String::from_utf8(bytes).map_err(|_| invalid_utf8()));
+            }
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+/// Returns the end of the first part of `text` tokenized in its own lattice, long texts are cut
+/// where every path converges before `MAX_BUFFER_LEN`.
+fn piece_end(tokenizer: &Tokenizer, text: &str) -> usize {
+    if text.len() <= MAX_BUFFER_LEN {
+        return text.len();
+    }
+
+    let limit = floor_char_boundary(text, MAX_BUFFER_LEN);
+    let window = &text[..floor_char_boundary(text, limit + CUT_MARGIN)];
+
+    tokenizer
+        .convergence_point(window, limit)
+        .filter(|&point| point > 0)
+        .unwrap_or(limit)
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+/// Returns the start of the last sentence, the text before it only contains complete sentences.
+/// The whole text is complete when it ends with a line break.
+fn find_sentence_cut(text: &str) -> Option<usize> {
+    if text.ends_with('\n') {
+        return Some(text.len());
+    }
+
+    Sentences::new(text)
+        .last()
+        .map(|sentence| sentence.start)
+        .filter(|&start| start > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_sentence_cut() {
+        assert_eq!(Some(21), find_sentence_cut("東京都に住む。ケーキを"));
+        assert_eq!(Some(19), find_sentence_cut("東京都に住む\nケーキを"));
+        assert_eq!(None, find_sentence_cut("「東京都に住む。ケーキを"));
+        assert_eq!(None, find_sentence_cut("東京都に住む。"));
+        assert_eq!(Some(22), find_sentence_cut("東京都に住む。\n"));
+    }
+
+    #[test]
+    fn test_reader_chunks() {
+        let text = "東京都に住む。\n".repeat(2000);
+        let reader = io::BufReader::with_capacity(5, text.as_bytes());
+        let chunks = ReaderChunks::new(reader)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        // at most 3 bytes of a split character are carried over
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 5 + 3));
+        assert_eq!(text, chunks.concat());
+
+        let chunks = ReaderChunks::new(text.as_bytes())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert!(chunks.iter().all(|chunk| chunk.len() <= READ_CHUNK_LEN + 3));
+        assert_eq!(text, chunks.concat());
+
+        let mut chunks = ReaderChunks::new(&text.as_bytes()[..text.len() - 2]);
+        let error = chunks.by_ref().find_map(Result::err).unwrap();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        let bytes = [b'a', 0xff, b'b'];
+        let error = ReaderChunks::new(&bytes[..]).find_map(Result::err).unwrap();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::morpheme::Morpheme;
+
+use mecab_types::{
+    features::Feature, meta::DictionaryKind, term::TermSource, unidic::UnidicFeature,
+};
+
+/// Morpheme borrowing its surface from the input and its features from the dictionary,
+/// the typed features are only parsed on demand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+    pub source: TermSource,
+    raw_feature: &'a str,
+    kind: DictionaryKind,
+}
+
+impl<'a> Token<'a> {
+    pub(crate) fn new(
+        text: &'a str,
+        start: usize,
+        source: TermSource,
+        raw_feature: &'a str,
+        kind: DictionaryKind,
+    ) -> Self {
+        Self {
+            text,
+            start,
+            end: start + text.len(),
+            source,
+            raw_feature,
+            kind,
+        }
+    }
+
+    /// Feature columns as stored in the dictionary.
+    pub fn raw_features(&self) -> &'a str {
+        self.raw_feature
+    }
+
+    /// Returns the feature column at a 0-based index, `*` columns included.
+    pub fn feature(&self, index: usize) -> Option<&'a str> {
+        self.raw_feature.split(',').nth(index)
+    }
+
+    /// Parses the typed features, UniDic labels are mapped to the ipadic ones.
+    pub fn parse_feature(&self) -> Feature {
+        parse_feature(self.raw_feature, self.kind)
+    }
+
+    pub fn to_morpheme(&self) -> Morpheme {
+        let mut morpheme = Morpheme::with_raw_feature(
+            self.text.to_owned(),
+            self.start,
+            self.end,
+            self.parse_feature(),
+            self.raw_feature.to_owned(),
+        );
+
+        if self.kind == DictionaryKind::Unidic {
+            morpheme.unidic = Some(Box::new(UnidicFeature::parse(self.raw_feature)));
+        }
+
+        morpheme
+    }
+}
+
+impl From<Token<'_>> for Morpheme {
+    fn from(value: Token<'_>) -> Self {
+        value.to_morpheme()
+    }
+}
+
+/// Parses raw feature columns laid out as the given dictionary kind, UniDic labels are mapped to
+/// the ipadic ones and malformed ipadic features give the default feature.
+pub(crate) fn parse_feature(raw_feature: &str, kind: DictionaryKind) -> Feature {
+    match kind {
+        DictionaryKind::Ipadic => Feature::parse(raw_feature).unwrap_or_default(),
+        DictionaryKind::Unidic => Feature::from(&UnidicFeature::parse(raw_feature)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mecab_types::pos::PartOfSpeech;
+
+    #[test]
+    fn test_to_morpheme() {
+        let input = "東京に";
+        let raw = "名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー";
+        let token = Token::new(
+            &input[..6],
+            0,
+            TermSource::System,
+            raw,
+            DictionaryKind::Ipadic,
+        );
+
+        assert_eq!(6, token.end);
+        assert_eq!(Some("トウキョウ"), token.feature(7));
+        assert_eq!(PartOfSpeech::Noun, token.parse_feature().part_of_speech);
+
+        let morpheme = Morpheme::from(token);
+
+        assert_eq!("東京", morpheme.text);
+        assert_eq!(Some("トーキョー".to_owned()), morpheme.pronunciation);
+        assert_eq!(raw, morpheme.raw_features());
+        assert!(morpheme.unidic.is_none());
+
+        let raw = "名詞,固有名詞,地名,一般,*,*,トウキョウ,東京,東京,トーキョー,東京,トーキョー,固";
+        let token = Token::new(
+            &input[..6],
+            0,
+            TermSource::System,
+            raw,
+            DictionaryKind::Unidic,
+        );
+        let morpheme = token.to_morpheme();
+
+        assert_eq!(
+            token.parse_feature().part_of_speech,
+            morpheme.part_of_speech
+        );
+        assert_eq!(Some("固".to_owned()), morpheme.unidic.unwrap().word_origin);
+    }
+}
@@ -1,83 +1,416 @@
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    fs::{self, File},
+    io::BufRead,
+    path::Path,
+    sync::OnceLock,
+};
+
 use mecab_types::{
-    bincode::decode_slice, char::CharTable, cost::CostMatrix, dict::EntryDictionary,
-    term::ExtractedTerm, unk::UnknownDictionary,
+    blob::Blob,
+    char::{CharCategory, CharTable},
+    cost::CostMatrix,
+    dict::EntryDictionary,
+    features::Feature,
+    meta::Metadata,
+    term::{ExtractedTerm, TermId, TermSource},
+    unk::UnknownDictionary,
 };
+use memmap2::Mmap;
 
 use crate::{
-    consts::{CHAR_TABLE, COST_MATRIX, TERM_DICT, TERM_FST, UNK_DICT},
-    error::Result,
+    constraint::{Constraints, ForcedSpan},
+    consts::{
+        CHAR_TABLE_FILE, COST_MATRIX_FILE, METADATA_FILE, TERM_DICT_FILE, TERM_FST_FILE,
+        UNK_DICT_FILE,
+    },
+    error::{Error, Result},
     fst::FstSearcher,
-    lattice::{Lattice, Node},
+    graph::LatticeGraph,
+    lattice::{Lattice, Node, NodeId, END_ID},
+    mode::Mode,
     morpheme::Morpheme,
+    normalize::{Normalized, Normalizer},
+    offset::{span_offsets, OffsetCounter},
+    sentence::{Sentence, Sentences},
+    stream::{MorphemeStream, ReaderChunks},
+    token::{parse_feature, Token},
+    user_dict::{Segment, TermEstimates, UserDictionary},
     word::Word,
 };
 
 pub struct Tokenizer {
     fst: FstSearcher,
-    dict: EntryDictionary,
+    pub(crate) dict: EntryDictionary,
     unk_dict: UnknownDictionary,
     char_table: CharTable,
     matrix: CostMatrix,
+    user_dict: Option<UserDictionary>,
+    term_estimates: OnceLock<TermEstimates>,
+    pub(crate) metadata: Metadata,
+    normalizer: Option<Normalizer>,
+    mode: Mode,
+    offsets: bool,
+}
+
+/// Input of a tokenization, along with its normalized form when a normalizer is set.
+struct Source<'a> {
+    input: &'a str,
+    normalized: Option<Normalized>,
+}
+
+/// Part of a path in the tokenized text, `node` being the index of the node it comes from.
+#[derive(Debug, Clone, Copy)]
+struct Span<'a> {
+    start: usize,
+    end: usize,
+    source: TermSource,
+    raw_feature: &'a str,
+    node: usize,
+}
+
+impl Source<'_> {
+    fn text(&self) -> &str {
+        self.normalized
+            .as_ref()
+            .map_or(self.input, |normalized| normalized.text.as_str())
+    }
+
+    /// Maps a span of the normalized text to the original input.
+    fn restore<'a>(&self, span: Span<'a>) -> Span<'a> {
+        match &self.normalized {
+            Some(normalized) => Span {
+                start: normalized.original_start(span.start),
+                end: normalized.original_end(span.end),
+                ..span
+            },
+            None => span,
+        }
+    }
 }
 
 impl Tokenizer {
+    /// Creates a tokenizer using the dictionary embedded at compile time.
+    #[cfg(feature = "embedded")]
     pub fn new() -> Result<Self> {
+        use crate::consts::{CHAR_TABLE, COST_MATRIX, METADATA, TERM_DICT, TERM_FST, UNK_DICT};
+
+        let tokenizer = Self::from_bytes(TERM_FST, TERM_DICT, UNK_DICT, CHAR_TABLE, COST_MATRIX)?;
+
+        Ok(tokenizer.with_metadata(Metadata::decode(METADATA)?))
+    }
+
+    /// Loads a dictionary from a directory containing the files generated by `mecab-processor`.
+    ///
+    /// The files are memory-mapped, the term dictionary and the cost matrix are read in place.
+    /// The dictionary is assumed to be ipadic when there is no metadata file.
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let map = |filename: &str| -> Result<Blob> {
+            let file = File::open(path.join(filename))?;
+            // SAFETY: the dictionary files are expected not to be modified while they are mapped
+            let mmap = unsafe { Mmap::map(&file)? };
+            Ok(Blob::new(mmap))
+        };
+
+        let metadata_path = path.join(METADATA_FILE);
+        let metadata = match metadata_path.exists() {
+            true => Metadata::decode(&fs::read(metadata_path)?)?,
+            false => Metadata::default(),
+        };
+        let tokenizer = Self::from_bytes(
+            map(TERM_FST_FILE)?,
+            map(TERM_DICT_FILE)?,
+            map(UNK_DICT_FILE)?,
+            map(CHAR_TABLE_FILE)?,
+            map(COST_MATRIX_FILE)?,
+        )?;
+
+        Ok(tokenizer.with_metadata(metadata))
+    }
+
+    /// Loads a dictionary from the raw contents of the files generated by `mecab-processor`,
+    /// each file can be given as static bytes, an owned buffer or a memory map.
+    ///
+    /// The dictionary is assumed to be ipadic, the contents of `meta.bin` are given with
+    /// [`Tokenizer::with_metadata`] and [`Metadata::decode`].
+    pub fn from_bytes(
+        term_fst: impl Into<Blob>,
+        term_dict: impl Into<Blob>,
+        unk_dict: impl Into<Blob>,
+        char_table: impl Into<Blob>,
+        cost_matrix: impl Into<Blob>,
+    ) -> Result<Self> {
+        let dict = EntryDictionary::new(term_dict.into())?;
+        let unk_dict = UnknownDictionary::decode(&unk_dict.into())?;
+        let matrix = CostMatrix::new(cost_matrix.into())?;
+
+        check_context_ids(&matrix, dict.max_context_ids())?;
+        check_context_ids(&matrix, unk_dict.max_context_ids())?;
+
         Ok(Self {
-            fst: FstSearcher::load(TERM_FST)?,
-            dict: decode_slice(TERM_DICT)?,
-            unk_dict: decode_slice(UNK_DICT)?,
-            char_table: decode_slice(CHAR_TABLE)?,
-            matrix: decode_slice(COST_MATRIX)?,
+            fst: FstSearcher::load(term_fst.into())?,
+            dict,
+            unk_dict,
+            char_table: CharTable::decode(&char_table.into())?,
+            matrix,
+            user_dict: None,
+            term_estimates: OnceLock::new(),
+            metadata: Metadata::default(),
+            normalizer: None,
+            mode: Mode::default(),
+            offsets: false,
         })
     }
 
+    /// Sets the dictionary metadata, which selects how features are parsed.
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Normalizes the input before tokenizing it, the offsets of the morphemes still refer to the
+    /// original input.
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Sets the segmentation mode, long compounds are split in the search modes.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Computes the char and UTF-16 offsets of the morphemes and words alongside the byte offsets.
+    pub fn with_offsets(mut self, enabled: bool) -> Self {
+        self.offsets = enabled;
+        self
+    }
+
+    /// Adds a user dictionary whose entries are looked up alongside the system dictionary.
+    pub fn with_user_dict(mut self, user_dict: UserDictionary) -> Result<Self> {
+        check_context_ids(&self.matrix, user_dict.max_context_ids())?;
+
+        self.user_dict = Some(user_dict);
+
+        Ok(self)
+    }
+
+    /// Estimates of the terms of the simplified user entries, built on first use.
+    pub(crate) fn term_estimates(&self) -> &TermEstimates {
+        self.term_estimates
+            .get_or_init(|| TermEstimates::new(&self.dict))
+    }
+
     pub fn tokenize(&self, input: &str) -> Vec<Morpheme> {
-        let text_len = input.len();
-        let mut lattice = Lattice::new(text_len);
+        let source = self.source(input);
+        let mut lattice = self.build_lattice(source.text(), &Constraints::default());
+        let nodes = lattice.find_path(&self.matrix);
 
-        for index in 0..text_len {
-            if !lattice.has_node_ending_at(index) {
-                continue;
-            }
+        self.build_morphemes(&source, &nodes)
+    }
 
-            let substr = &input[index..];
-            let mut extracted = self.get_terms_from_str(substr);
-            let found = !extracted.is_empty();
-            let unknown = self.get_unkown_terms_from_str(substr, found);
+    /// Tokenizes the input without copying the text and features of each morpheme, the tokens
+    /// borrow from the input and the dictionary. The lattice and the returned vector are still
+    /// allocated. When a normalizer is set, the tokens borrow their text from the original input.
+    pub fn tokenize_borrowed<'a>(&'a self, input: &'a str) -> Vec<Token<'a>> {
+        let source = self.source(input);
+        let mut lattice = self.build_lattice(source.text(), &Constraints::default());
+        let nodes = lattice.find_path(&self.matrix);
+        let spans = restore_spans(&source, self.build_spans(source.text(), &nodes), true);
 
-            extracted.extend(unknown);
+        self.build_tokens(input, &spans)
+    }
 
-            for term in extracted {
-                lattice.add_node(Node::new(
-                    term.id,
-                    term.unknown,
-                    index,
-                    index + term.length,
-                    term.value.context_id,
-                    term.value.cost,
-                ));
+    /// Tokenizes the input while respecting known boundaries and forced spans.
+    pub fn tokenize_with_constraints(
+        &self,
+        input: &str,
+        constraints: &Constraints,
+    ) -> Result<Vec<Morpheme>> {
+        constraints.validate(input)?;
+
+        let source = self.source(input);
+        let mapped = match &source.normalized {
+            Some(normalized) => {
+                constraints.map_offsets(|index| normalized.normalized_offset(index))?
+            }
+            None => constraints.clone(),
+        };
+        let mut lattice = self.build_lattice(source.text(), &mapped);
+
+        for (span, mapped_span) in constraints.spans.iter().zip(&mapped.spans) {
+            if !lattice.has_node(mapped_span.start, mapped_span.end) {
+                let pos = span
+                    .part_of_speech
+                    .as_ref()
+                    .map_or(String::new(), |pos| format!(" with part of speech {}", pos));
+
+                return Err(Error::InvalidConstraint(format!(
+                    "No candidate for the span {}..{}{}",
+                    span.start, span.end, pos
+                )));
             }
         }
 
         let nodes = lattice.find_path(&self.matrix);
-        let mut tokens = Vec::new();
 
-        for node in nodes {
-            let end = match node.end > text_len {
-                true => text_len,
-                false => node.end,
-            };
-            let text = input[node.start..end].to_owned();
-            let feature = match node.unknown {
-                true => self.unk_dict.get_feat(node.term_id),
-                false => self.dict.get_feature(node.term_id),
-            };
-            let feature = feature.cloned().unwrap_or_default();
+        Ok(self.build_morphemes(&source, &nodes))
+    }
+
+    /// Returns the `n` lowest-cost segmentations with their total cost in ascending order.
+    /// Paths giving the same morphemes as a cheaper one (e.g. a segmented user entry and the
+    /// system words it is made of) are skipped.
+    pub fn tokenize_nbest(&self, input: &str, n: usize) -> Vec<(Vec<Morpheme>, i32)> {
+        let source = self.source(input);
+        let mut lattice = self.build_lattice(source.text(), &Constraints::default());
+        let mut seen = HashSet::new();
+
+        lattice
+            .nbest_paths(&self.matrix)
+            .map(|(nodes, cost)| (self.build_morphemes(&source, &nodes), cost))
+            .filter(|(morphemes, _)| {
+                seen.insert(
+                    morphemes
+                        .iter()
+                        .map(|morpheme| {
+                            (
+                                morpheme.start,
+                                morpheme.end,
+                                morpheme.raw_features().to_owned(),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .take(n)
+            .collect()
+    }
+
+    /// Tokenizes the input and computes the marginal probability of each morpheme,
+    /// `theta` scales the costs before they are turned into probabilities.
+    pub fn tokenize_with_probabilities(&self, input: &str, theta: f64) -> Vec<Morpheme> {
+        let source = self.source(input);
+        let mut lattice = self.build_lattice(source.text(), &Constraints::default());
+
+        lattice.find_path(&self.matrix);
+
+        let probabilities = lattice.marginal_probabilities(&self.matrix, theta);
+        let path = lattice.best_path_ids();
+        let nodes = path
+            .get(1..path.len().saturating_sub(1))
+            .unwrap_or_default();
+
+        self.build_morphemes_with_probabilities(&source, &lattice, nodes, &probabilities, true)
+    }
 
-            tokens.push(Morpheme::new(text, node.start, end, feature));
+    /// Returns every candidate morpheme with a non-zero marginal probability,
+    /// ordered by position and by decreasing probability.
+    pub fn alternatives(&self, input: &str, theta: f64) -> Vec<Morpheme> {
+        let source = self.source(input);
+        let lattice = self.build_lattice(source.text(), &Constraints::default());
+        let probabilities = lattice.marginal_probabilities(&self.matrix, theta);
+        let nodes: Vec<_> = (END_ID + 1..lattice.nodes().len())
+            .filter(|&id| probabilities[id] > 0.0)
+            .collect();
+        let mut morphemes = self.build_morphemes_with_probabilities(
+            &source,
+            &lattice,
+            &nodes,
+            &probabilities,
+            false,
+        );
+
+        morphemes.sort_by(|a, b| {
+            let probability = |morpheme: &Morpheme| morpheme.probability.unwrap_or_default();
+
+            a.start
+                .cmp(&b.start)
+                .then(probability(b).total_cmp(&probability(a)))
+        });
+
+        morphemes
+    }
+
+    /// Builds the full lattice of candidates for an input, with the best path highlighted.
+    /// The offsets refer to the original input when a normalizer is set.
+    pub fn lattice(&self, input: &str) -> LatticeGraph {
+        let source = self.source(input);
+        let mut lattice = self.build_lattice(source.text(), &Constraints::default());
+
+        lattice.find_path(&self.matrix);
+
+        let mut graph = LatticeGraph::new(&lattice, &self.matrix, |node| {
+            let span = source.restore(self.build_span(source.text(), node, 0));
+            self.build_tokens(input, &[span])[0].to_morpheme()
+        });
+
+        if let Some(normalized) = &source.normalized {
+            for node in &mut graph.nodes {
+                node.start = normalized.original_start(node.start);
+                node.end = normalized.original_end(node.end);
+            }
         }
 
-        tokens
+        graph
+    }
+
+    /// Splits a document into sentences.
+    pub fn sentences<'a>(&self, text: &'a str) -> Sentences<'a> {
+        Sentences::new(text)
+    }
+
+    /// Tokenizes a document sentence by sentence, the offsets of the morphemes refer to the
+    /// whole document.
+    pub fn tokenize_sentences<'a>(&self, text: &'a str) -> Vec<(Sentence<'a>, Vec<Morpheme>)> {
+        let mut counter = OffsetCounter::new(text);
+
+        self.sentences(text)
+            .map(|sentence| {
+                let base = counter.offsets(sentence.start, sentence.end);
+                let mut morphemes = self.tokenize(sentence.text);
+
+                for morpheme in &mut morphemes {
+                    morpheme.start += sentence.start;
+                    morpheme.end += sentence.start;
+
+                    if let Some(offsets) = &mut morpheme.offsets {
+                        offsets.shift(&base);
+                    }
+                }
+
+                (sentence, morphemes)
+            })
+            .collect()
+    }
+
+    /// Tokenizes a text read in chunks, the morphemes are yielded as soon as a sentence is
+    /// complete and their offsets refer to the whole text.
+    pub fn tokenize_chunks<'a, I>(&'a self, chunks: I) -> impl Iterator<Item = Morpheme> + 'a
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: 'a,
+    {
+        let chunks = chunks.into_iter().map(Ok::<_, Infallible>);
+
+        MorphemeStream::new(self, chunks).map(|result| match result {
+            Ok(morpheme) => morpheme,
+            Err(never) => match never {},
+        })
+    }
+
+    /// Tokenizes a reader in chunks of UTF-8 text, see [`Tokenizer::tokenize_chunks`].
+    pub fn tokenize_reader<'a, R>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<Morpheme>> + 'a
+    where
+        R: BufRead + 'a,
+    {
+        MorphemeStream::new(self, ReaderChunks::new(reader))
+            .map(|result| result.map_err(Error::from))
     }
 
     pub fn tokenize_word(&self, input: &str) -> Vec<Word> {
@@ -113,57 +446,597 @@ impl Tokenizer {
         words
     }
 
-    fn get_terms_from_str(&self, input: &str) -> Vec<ExtractedTerm> {
-        let terms = self.fst.get_from_prefix(input);
-        let mut extracted = Vec::new();
+    fn build_lattice(&self, input: &str, constraints: &Constraints) -> Lattice {
+        let text_len = input.len();
+        let mut lattice = Lattice::new(text_len);
+
+        for index in 0..text_len {
+            if !lattice.has_node_ending_at(index) {
+                continue;
+            }
+
+            let substr = &input[index..];
+            let mut extracted = self.get_terms_from_str(substr);
+
+            extracted.retain(|term| self.satisfies(constraints, index, term));
+
+            let found = !extracted.is_empty();
+            let mut unknown = self.get_unkown_terms_from_str(substr, found);
+
+            unknown.retain(|term| self.satisfies(constraints, index, term));
+            extracted.extend(unknown);
+
+            if extracted.is_empty() {
+                extracted = self.get_fallback_terms(substr, constraints.span_starting_at(index));
+            }
+
+            for term in extracted {
+                let cost = self.penalize(&substr[..term.length], term.value.cost);
+
+                lattice.add_node(Node::new(
+                    term.id,
+                    term.source,
+                    index,
+                    index + term.length,
+                    term.value.left_id,
+                    term.value.right_id,
+                    cost,
+                ));
+            }
+        }
+
+        lattice
+    }
+
+    /// Adds the length penalty of the search modes to the cost of a word.
+    fn penalize(&self, text: &str, cost: i16) -> i32 {
+        let Some(penalty) = self.mode.penalty() else {
+            return cost as i32;
+        };
+
+        let length = text.chars().count();
+        let is_kanji = text.chars().all(|ch| {
+            self.char_table
+                .lookup(ch)
+                .iter()
+                .any(|category| category.name.starts_with("KANJI"))
+        });
+        let penalty = penalty.get(length, is_kanji);
+
+        (cost as i32).saturating_add(penalty)
+    }
+
+    /// Returns the last offset up to `limit` where every path of the lattice converges.
+    pub(crate) fn convergence_point(&self, input: &str, limit: usize) -> Option<usize> {
+        self.build_lattice(input, &Constraints::default())
+            .last_convergence_point(limit)
+    }
+
+    fn source<'a>(&self, input: &'a str) -> Source<'a> {
+        Source {
+            input,
+            normalized: self
+                .normalizer
+                .map(|normalizer| normalizer.normalize(input)),
+        }
+    }
+
+    fn build_morphemes_with_probabilities(
+        &self,
+        source: &Source,
+        lattice: &Lattice,
+        ids: &[NodeId],
+        probabilities: &[f64],
+        merge: bool,
+    ) -> Vec<Morpheme> {
+        let nodes: Vec<_> = ids.iter().map(|&id| lattice.nodes()[id].clone()).collect();
+        let spans = restore_spans(source, self.build_spans(source.text(), &nodes), merge);
+        let tokens = self.build_tokens(source.input, &spans);
+        let mut morphemes: Vec<_> = spans
+            .iter()
+            .zip(tokens)
+            .map(|(span, token)| {
+                let mut morpheme = token.to_morpheme();
+                morpheme.probability = Some(probabilities[ids[span.node]]);
+                morpheme
+            })
+            .collect();
+
+        self.compute_offsets(source, &mut morphemes);
+
+        morphemes
+    }
 
-        for (len, id) in terms {
-            if let Some(term) = self.dict.get_term(id) {
-                extracted.push(ExtractedTerm::new(id, false, len, term.clone()));
+    fn build_morphemes(&self, source: &Source, nodes: &[Node]) -> Vec<Morpheme> {
+        let spans = restore_spans(source, self.build_spans(source.text(), nodes), true);
+        let mut morphemes: Vec<_> = self
+            .build_tokens(source.input, &spans)
+            .iter()
+            .map(Token::to_morpheme)
+            .collect();
+
+        self.compute_offsets(source, &mut morphemes);
+
+        morphemes
+    }
+
+    fn compute_offsets(&self, source: &Source, morphemes: &mut [Morpheme]) {
+        if !self.offsets {
+            return;
+        }
+
+        // the alternatives overlap, the offsets are converted in a single pass over the input
+        let spans: Vec<_> = morphemes
+            .iter()
+            .map(|morpheme| (morpheme.start, morpheme.end))
+            .collect();
+
+        for (morpheme, offsets) in morphemes.iter_mut().zip(span_offsets(source.input, &spans)) {
+            morpheme.offsets = Some(offsets);
+        }
+    }
+
+    fn build_spans<'a>(&'a self, text: &str, nodes: &[Node]) -> Vec<Span<'a>> {
+        let mut spans = Vec::new();
+
+        for (index, node) in nodes.iter().enumerate() {
+            if let Some(segments) = self.get_segments(node) {
+                let mut start = node.start;
+
+                for segment in segments {
+                    let end = start + segment.length;
+                    spans.push(Span {
+                        start,
+                        end,
+                        source: node.source,
+                        raw_feature: &segment.feature,
+                        node: index,
+                    });
+                    start = end;
+                }
+
+                continue;
+            }
+
+            let span = self.build_span(text, node, index);
+
+            match (self.mode, node.source) {
+                (Mode::Extended(_), TermSource::Unknown) => {
+                    spans.extend(
+                        text[span.start..span.end]
+                            .char_indices()
+                            .map(|(index, ch)| {
+                                let start = span.start + index;
+
+                                Span {
+                                    start,
+                                    end: start + ch.len_utf8(),
+                                    ..span
+                                }
+                            }),
+                    );
+                }
+                _ => spans.push(span),
+            }
+        }
+
+        spans
+    }
+
+    fn build_span<'a>(&'a self, text: &str, node: &Node, index: usize) -> Span<'a> {
+        Span {
+            start: node.start,
+            end: node.end.min(text.len()),
+            source: node.source,
+            raw_feature: self
+                .get_raw_feature(node.source, node.term_id)
+                .unwrap_or_default(),
+            node: index,
+        }
+    }
+
+    fn build_tokens<'a>(&'a self, input: &'a str, spans: &[Span<'a>]) -> Vec<Token<'a>> {
+        spans
+            .iter()
+            .map(|span| {
+                self.new_token(
+                    &input[span.start..span.end],
+                    span.start,
+                    span.source,
+                    span.raw_feature,
+                )
+            })
+            .collect()
+    }
+
+    fn new_token<'a>(
+        &self,
+        text: &'a str,
+        start: usize,
+        source: TermSource,
+        feature: &'a str,
+    ) -> Token<'a> {
+        Token::new(text, start, source, feature, self.metadata.kind)
+    }
+
+    fn get_raw_feature(&self, source: TermSource, id: TermId) -> Option<&str> {
+        match source {
+            TermSource::System => self.dict.get_raw_feature(id),
+            TermSource::User => self
+                .user_dict
+                .as_ref()
+                .and_then(|user_dict| user_dict.dict.get_raw_feature(id)),
+            TermSource::Unknown => self.unk_dict.get_raw_feature(id),
+        }
+    }
+
+    fn get_feature(&self, source: TermSource, id: TermId) -> Option<Feature> {
+        self.get_raw_feature(source, id)
+            .map(|feature| parse_feature(feature, self.metadata.kind))
+    }
+
+    fn satisfies(&self, constraints: &Constraints, start: usize, term: &ExtractedTerm) -> bool {
+        let end = start + term.length;
+
+        if !constraints.allows(start, end) {
+            return false;
+        }
+
+        match constraints.get_span(start, end) {
+            Some(ForcedSpan {
+                part_of_speech: Some(pos),
+                ..
+            }) => self
+                .get_feature(term.source, term.id)
+                .is_some_and(|feature| feature.part_of_speech == *pos),
+            _ => true,
+        }
+    }
+
+    /// Unknown terms used when the constraints removed every candidate, either covering
+    /// a whole forced span or a single character. The terms of a forced span must have its
+    /// part of speech, the span is left without candidates otherwise.
+    fn get_fallback_terms(&self, input: &str, span: Option<&ForcedSpan>) -> Vec<ExtractedTerm> {
+        let ch = input.chars().next().unwrap();
+        let length = span.map_or(ch.len_utf8(), |span| span.end - span.start);
+        let mut terms = Vec::new();
+
+        for category in self.char_table.lookup(ch) {
+            if let Some(values) = self.unk_dict.get_terms(&category.name) {
+                for (id, value) in values {
+                    terms.push(ExtractedTerm::new(*id, TermSource::Unknown, length, *value));
+                }
             }
         }
 
+        if let Some(pos) = span.and_then(|span| span.part_of_speech.as_ref()) {
+            terms.retain(|term| {
+                self.get_feature(term.source, term.id)
+                    .is_some_and(|feature| feature.part_of_speech == *pos)
+            });
+        }
+
+        terms
+    }
+
+    fn get_segments(&self, node: &Node) -> Option<&[Segment]> {
+        match node.source {
+            TermSource::User => self.user_dict.as_ref()?.get_segments(node.term_id),
+            _ => None,
+        }
+    }
+
+    fn get_terms_from_str(&self, input: &str) -> Vec<ExtractedTerm> {
+        let mut extracted = extract_terms(&self.fst, &self.dict, TermSource::System, input);
+
+        if let Some(user_dict) = &self.user_dict {
+            let user_terms =
+                extract_terms(&user_dict.fst, &user_dict.dict, TermSource::User, input);
+            extracted.extend(user_terms);
+        }
+
         extracted
     }
 
     fn get_unkown_terms_from_str(&self, input: &str, found: bool) -> Vec<ExtractedTerm> {
         let mut unk_terms = Vec::new();
-        let mut chars = input.chars();
-        let mut current_len = 0;
-        let ch = chars.next().unwrap();
+        let ch = input.chars().next().unwrap();
         let char_categories = self.char_table.lookup(ch);
 
-        current_len += ch.len_utf8();
-
         for category in char_categories {
             if found && !category.invoke {
                 continue;
             }
 
-            if category.group {
-                for ch in chars.by_ref() {
-                    if self.char_table.lookup(ch).contains(category) {
-                        current_len += ch.len_utf8();
-                    } else {
-                        break;
+            if let Some(terms) = self.unk_dict.get_terms(&category.name) {
+                for length in get_unknown_lengths(input, category, &self.char_table) {
+                    for (id, value) in terms {
+                        unk_terms.push(ExtractedTerm::new(
+                            *id,
+                            TermSource::Unknown,
+                            length,
+                            *value,
+                        ));
                     }
                 }
             }
+        }
 
-            if let Some(terms) = self.unk_dict.get_terms(&category.name) {
-                for (id, value) in terms {
-                    unk_terms.push(ExtractedTerm::new(*id, true, current_len, value.clone()));
+        unk_terms
+    }
+}
+
+/// Checks that the largest context ids of a dictionary are within the cost matrix.
+fn check_context_ids(matrix: &CostMatrix, max_ids: Option<(u16, u16)>) -> Result<()> {
+    if let Some((left_id, right_id)) = max_ids {
+        if left_id as usize >= matrix.cols() {
+            return Err(Error::ContextIdOutOfBounds(left_id));
+        }
+
+        if right_id as usize >= matrix.rows() {
+            return Err(Error::ContextIdOutOfBounds(right_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Maps the spans of a path back to the original input. A character expanded into several
+/// morphemes by the normalizer (e.g. ㈱ into `(`, `株` and `)`) gives overlapping spans which
+/// are merged into a single morpheme, keeping the features of the longest one.
+fn restore_spans<'a>(source: &Source, spans: Vec<Span<'a>>, merge: bool) -> Vec<Span<'a>> {
+    if source.normalized.is_none() {
+        return spans;
+    }
+
+    let mut restored: Vec<(Span, usize)> = Vec::with_capacity(spans.len());
+
+    for span in spans {
+        let length = span.end - span.start;
+        let span = source.restore(span);
+
+        match restored.last_mut() {
+            Some((last, last_length)) if merge && span.start < last.end => {
+                let (start, end) = (last.start.min(span.start), last.end.max(span.end));
+
+                if length > *last_length {
+                    (*last, *last_length) = (span, length);
                 }
+
+                last.start = start;
+                last.end = end;
             }
+            _ => restored.push((span, length)),
         }
+    }
 
-        unk_terms
+    restored.into_iter().map(|(span, _)| span).collect()
+}
+
+/// Byte lengths of the unknown candidates starting at the input: every prefix of up to
+/// `category.length` characters and the whole run when the category is grouped.
+fn get_unknown_lengths(input: &str, category: &CharCategory, char_table: &CharTable) -> Vec<usize> {
+    let mut lengths = Vec::new();
+    let mut current_len = 0;
+
+    for (count, ch) in input.chars().enumerate() {
+        if count > 0 && !char_table.lookup(ch).contains(category) {
+            break;
+        }
+
+        if !category.group && count >= category.length {
+            break;
+        }
+
+        current_len += ch.len_utf8();
+
+        if count < category.length {
+            lengths.push(current_len);
+        }
+    }
+
+    if category.group && !lengths.contains(&current_len) {
+        lengths.push(current_len);
     }
+
+    if lengths.is_empty() {
+        lengths.extend(input.chars().next().map(char::len_utf8));
+    }
+
+    lengths
+}
+
+fn extract_terms(
+    fst: &FstSearcher,
+    dict: &EntryDictionary,
+    source: TermSource,
+    input: &str,
+) -> Vec<ExtractedTerm> {
+    let surfaces = fst.get_from_prefix(input);
+    let mut extracted = Vec::new();
+
+    for (len, index) in surfaces {
+        for id in dict.get_range(index).into_iter().flatten() {
+            if let Some(term) = dict.get_term(id) {
+                extracted.push(ExtractedTerm::new(id, source, len, term));
+            }
+        }
+    }
+
+    extracted
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{tokenize, tokenize_word};
+    use super::{get_unknown_lengths, restore_spans, Source, Span, Tokenizer};
+
+    use crate::{
+        error::{Error, Result},
+        mode::{Mode, Penalty},
+        morpheme::Morpheme,
+        Constraints, Normalizer,
+    };
+
+    use std::collections::BTreeMap;
+
+    use fst::MapBuilder;
+    use mecab_types::{
+        char::{CharCategory, CharTable},
+        cost::CostMatrix,
+        dict::EntryDictionary,
+        pos::PartOfSpeech,
+        term::{Term, TermSource},
+        unk::UnknownDictionary,
+        utils::FlatMatrix,
+    };
+
+    /// Builds a tokenizer from a dictionary holding a single 東京 entry and an unknown template
+    /// for the default category, which groups characters.
+    fn build_tokenizer(term: Term, unk_term: Term, size: usize) -> Result<Tokenizer> {
+        let mut fst = MapBuilder::memory();
+        let mut dict = Vec::new();
+        let mut unk_dict = Vec::new();
+        let mut char_table = Vec::new();
+        let mut matrix = Vec::new();
+        let unk_terms = BTreeMap::from([("DEFAULT".to_owned(), vec![(0, unk_term)])]);
+
+        fst.insert("東京", 0).unwrap();
+        EntryDictionary::encode(&[term], &["名詞".to_owned()], &[1], &mut dict).unwrap();
+        UnknownDictionary::new(unk_terms, vec!["名詞".to_owned()])
+            .encode(&mut unk_dict)
+            .unwrap();
+        CharTable::new(vec![CharCategory::new("DEFAULT".to_owned(), true, true, 1)])
+            .encode(&mut char_table)
+            .unwrap();
+        CostMatrix::encode(&FlatMatrix::new(size, size), &mut matrix).unwrap();
+
+        Tokenizer::from_bytes(
+            fst.into_inner().unwrap(),
+            dict,
+            unk_dict,
+            char_table,
+            matrix,
+        )
+    }
+
+    #[test]
+    fn test_context_ids_out_of_bounds() {
+        let unk_term = Term::new(1, 1, 100);
+
+        assert!(build_tokenizer(Term::new(3, 3, 100), unk_term, 4).is_ok());
+        assert!(matches!(
+            build_tokenizer(Term::new(3, 1, 100), unk_term, 2),
+            Err(Error::ContextIdOutOfBounds(3))
+        ));
+        assert!(matches!(
+            build_tokenizer(Term::new(1, 1, 100), Term::new(1, 5, 100), 2),
+            Err(Error::ContextIdOutOfBounds(5))
+        ));
+    }
+
+    #[test]
+    fn test_span_part_of_speech() {
+        let tokenizer = build_tokenizer(Term::new(1, 1, 100), Term::new(1, 1, 100), 2).unwrap();
+        let constraints = Constraints::new().with_span(0, 9, Some(PartOfSpeech::Noun));
+        let morphemes = tokenizer
+            .tokenize_with_constraints("東京都", &constraints)
+            .unwrap();
+
+        assert_eq!("東京都", morphemes[0].text);
+        assert!(matches!(
+            tokenizer.tokenize_with_constraints(
+                "東京都",
+                &Constraints::new().with_span(0, 9, Some(PartOfSpeech::Verb))
+            ),
+            Err(Error::InvalidConstraint(_))
+        ));
+    }
+
+    #[test]
+    fn test_tokenize_nbest_unique() {
+        let text = |results: Vec<(Vec<Morpheme>, i32)>| -> Vec<Vec<_>> {
+            results
+                .into_iter()
+                .map(|(morphemes, _)| morphemes.into_iter().map(|m| m.text).collect())
+                .collect()
+        };
+        let tokenizer = build_tokenizer(Term::new(1, 1, 100), Term::new(1, 1, 100), 2).unwrap();
+
+        assert_eq!(
+            vec![vec!["xyz"], vec!["x", "yz"], vec!["x", "y", "z"]],
+            text(tokenizer.tokenize_nbest("xyz", 5))
+        );
+
+        // every path splits the unknown words into the same characters
+        let tokenizer = tokenizer.with_mode(Mode::Extended(Penalty::default()));
+
+        assert_eq!(
+            vec![vec!["x", "y", "z"]],
+            text(tokenizer.tokenize_nbest("xyz", 5))
+        );
+    }
+
+    #[test]
+    fn test_restore_spans() {
+        let input = "㈱東京";
+        let source = Source {
+            input,
+            normalized: Some(Normalizer::new().normalize(input)),
+        };
+        let span = |start, end, raw_feature| Span {
+            start,
+            end,
+            source: TermSource::System,
+            raw_feature,
+            node: 0,
+        };
+        // (株)東京
+        let spans = vec![
+            span(0, 1, "記号"),
+            span(1, 4, "名詞"),
+            span(4, 5, "記号"),
+            span(5, 11, "名詞"),
+        ];
+        let restored: Vec<_> = restore_spans(&source, spans.clone(), true)
+            .iter()
+            .map(|span| (span.start, span.end, span.raw_feature))
+            .collect();
+
+        assert_eq!(vec![(0, 3, "名詞"), (3, 9, "名詞")], restored);
+        assert_eq!(4, restore_spans(&source, spans, false).len());
+    }
+
+    #[test]
+    fn test_unknown_lengths() {
+        let kanji = CharCategory::new("KANJI".to_owned(), false, false, 2);
+        let katakana = CharCategory::new("KATAKANA".to_owned(), true, true, 2);
+        let mut char_table = CharTable::new(vec![]);
+
+        char_table.insert('漢' as u32, '漢' as u32, vec![kanji.clone()]);
+        char_table.insert('字' as u32, '字' as u32, vec![kanji.clone()]);
+        char_table.insert('カ' as u32, 'カ' as u32, vec![katakana.clone()]);
+        char_table.insert('ナ' as u32, 'ナ' as u32, vec![katakana.clone()]);
+
+        assert_eq!(
+            vec![3, 6],
+            get_unknown_lengths("漢字漢", &kanji, &char_table)
+        );
+        assert_eq!(vec![3], get_unknown_lengths("漢カ", &kanji, &char_table));
+        assert_eq!(
+            vec![3, 6, 12],
+            get_unknown_lengths("カナカナ漢", &katakana, &char_table)
+        );
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod embedded_tests {
+    use crate::{
+        graph::GraphNodeKind,
+        mode::{Mode, Penalty},
+        morpheme::Morpheme,
+        tokenize, tokenize_word, Constraints, Normalizer, Token, Tokenizer, UserDictionary,
+    };
 
     use mecab_types::{
         conjugation::ConjugationForm as C,
@@ -197,6 +1070,7 @@ mod tests {
 
         assert_eq!(morphemes[1].part_of_speech, P::Particle);
         assert_eq!(Some("ヲ".to_owned()), morphemes[1].reading);
+        assert_eq!(Some("ヲ".to_owned()), morphemes[1].pronunciation);
 
         assert_eq!(morphemes[2].part_of_speech, P::Verb);
         assert_eq!(Some(C::BasicForm), morphemes[2].conjugation_form);
@@ -211,12 +1085,329 @@ mod tests {
         assert!(morphemes[1].sub_part_of_speech.contains(&S::Space));
     }
 
+    #[test]
+    fn test_offsets() {
+        let tokenizer = Tokenizer::new().unwrap().with_offsets(true);
+        let morphemes = tokenizer.tokenize("𠮷野家で食べた");
+        let first = morphemes[0].offsets.unwrap();
+        let last = morphemes.last().and_then(|m| m.offsets).unwrap();
+
+        assert_eq!((0, 0), (first.char_start, first.utf16_start));
+        assert_eq!((7, 8), (last.char_end, last.utf16_end));
+        assert!(Tokenizer::new().unwrap().tokenize("東京")[0]
+            .offsets
+            .is_none());
+
+        let words = tokenizer.tokenize_word("𠮷野家で食べた");
+        let last = words.last().and_then(|word| word.offsets).unwrap();
+
+        assert_eq!((7, 8), (last.char_end, last.utf16_end));
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let tokenizer = Tokenizer::new()
+            .unwrap()
+            .with_normalizer(Normalizer::new())
+            .with_offsets(true);
+        let input = "ｶﾞｽを使う";
+        let morphemes = tokenizer.tokenize(input);
+
+        assert_eq!("ｶﾞｽ", morphemes[0].text);
+        assert_eq!((0, 9), (morphemes[0].start, morphemes[0].end));
+        assert_eq!(Some("ガス".to_owned()), morphemes[0].base_form);
+        assert_eq!(3, morphemes[0].offsets.unwrap().char_end);
+
+        let constraints = Constraints::new().with_boundary(9);
+        let morphemes = tokenizer
+            .tokenize_with_constraints(input, &constraints)
+            .unwrap();
+
+        assert_eq!(9, morphemes[1].start);
+        assert!(tokenizer
+            .tokenize_with_constraints(input, &Constraints::new().with_boundary(3))
+            .is_err());
+
+        let morphemes = tokenizer.tokenize("㈱を買う");
+
+        assert_eq!("㈱", morphemes[0].text);
+        assert_eq!((0, 3), (morphemes[0].start, morphemes[0].end));
+        assert_eq!("を", morphemes[1].text);
+    }
+
+    #[test]
+    fn test_tokenize_sentences() {
+        let tokenizer = Tokenizer::new().unwrap().with_offsets(true);
+        let text = "「雨だ！」と言った。\n𠮷野家に行く。";
+        let sentences = tokenizer.tokenize_sentences(text);
+
+        assert_eq!(2, sentences.len());
+        assert_eq!("「雨だ！」と言った。", sentences[0].0.text);
+
+        let (sentence, morphemes) = &sentences[1];
+        let last = morphemes.last().unwrap();
+
+        assert_eq!(sentence.start, morphemes[0].start);
+        assert_eq!("。", &text[last.start..last.end]);
+        assert_eq!(text.chars().count(), last.offsets.unwrap().char_end);
+    }
+
+    #[test]
+    fn test_tokenize_chunks() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let text = "東京都に住む。ケーキを食べる。昨日、彼に会った。";
+        let chunks = [
+            "東京都に",
+            "住む。ケー",
+            "キを食べる。昨日、",
+            "彼に会った。",
+        ];
+        let morphemes: Vec<_> = tokenizer.tokenize_chunks(chunks).collect();
+        let expected: Vec<_> = tokenizer
+            .tokenize_sentences(text)
+            .into_iter()
+            .flat_map(|(_, morphemes)| morphemes)
+            .collect();
+
+        assert_eq!(expected, morphemes);
+
+        let text = "東京都に住む\nケーキを食べる。\n";
+        let morphemes = tokenizer
+            .tokenize_reader(text.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(text.len() - 1, morphemes.last().unwrap().end);
+    }
+
+    #[test]
+    fn test_tokenize_large_chunks() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let sentences = |text: &str| -> Vec<_> {
+            tokenizer
+                .tokenize_sentences(text)
+                .into_iter()
+                .flat_map(|(_, morphemes)| morphemes)
+                .collect()
+        };
+
+        // a single chunk holding many sentences
+        let text = "東京都に住む。ケーキを食べる。\n".repeat(5000);
+        let morphemes: Vec<_> = tokenizer.tokenize_chunks([text.as_str()]).collect();
+
+        assert_eq!(sentences(&text), morphemes);
+
+        // a single line without any sentence end
+        let text = "東京都に住む".repeat(10000);
+        let morphemes = tokenizer
+            .tokenize_reader(text.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let chunks: Vec<_> = tokenizer.tokenize_chunks([text.as_str()]).collect();
+
+        assert_eq!(chunks, morphemes);
+        assert_eq!(0, morphemes[0].start);
+        assert_eq!(text.len(), morphemes.last().unwrap().end);
+        assert!(morphemes
+            .windows(2)
+            .all(|pair| pair[0].end == pair[1].start));
+        assert_eq!(
+            text.chars().count(),
+            morphemes.last().unwrap().offsets.unwrap().char_end
+        );
+    }
+
+    #[test]
+    fn test_search_mode() {
+        let text = |morphemes: Vec<Morpheme>| -> Vec<_> {
+            morphemes
+                .into_iter()
+                .map(|morpheme| morpheme.text)
+                .collect()
+        };
+        let tokenizer = Tokenizer::new().unwrap();
+
+        assert_eq!(
+            vec!["関西国際空港"],
+            text(tokenizer.tokenize("関西国際空港"))
+        );
+
+        let tokenizer = tokenizer.with_mode(Mode::Search(Penalty::default()));
+
+        assert_eq!(
+            vec!["関西", "国際", "空港"],
+            text(tokenizer.tokenize("関西国際空港"))
+        );
+
+        let tokenizer = tokenizer.with_mode(Mode::Extended(Penalty::default()));
+
+        assert_eq!(vec!["x", "y", "z"], text(tokenizer.tokenize("xyz")));
+    }
+
+    #[test]
+    fn test_tokenize_borrowed() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let input = "東京都に住む";
+        let tokens = tokenizer.tokenize_borrowed(input);
+        let morphemes: Vec<_> = tokens.iter().map(Token::to_morpheme).collect();
+
+        assert_eq!(tokenizer.tokenize(input), morphemes);
+        assert_eq!(Some("トウキョウ"), tokens[0].feature(7));
+
+        let tokenizer = tokenizer.with_normalizer(Normalizer::new());
+        let tokens = tokenizer.tokenize_borrowed("ｶﾞｽを使う");
+
+        assert_eq!(
+            ("ｶﾞｽ", 0, 9),
+            (tokens[0].text, tokens[0].start, tokens[0].end)
+        );
+    }
+
     #[test]
     fn test_tokenize_word() {
         let tokens = tokenize_word("昨日、彼に会った。すごく嬉しかったよ。").unwrap();
         let expected = vec!["昨日", "彼", "に", "会った", "すごく", "嬉しかった", "よ"];
-        let text: Vec<_> = tokens.into_iter().map(|token| token.text).collect();
+        let text: Vec<_> = tokens.iter().map(|token| token.text.as_str()).collect();
 
         assert_eq!(expected, text);
+        assert_eq!(Some("アッタ"), tokens[3].pronunciation.as_deref());
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let data_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../data");
+        let tokenizer = Tokenizer::from_dir(data_dir).unwrap();
+        let input = "東京都に住む";
+
+        assert_eq!(tokenize(input).unwrap(), tokenizer.tokenize(input));
+    }
+
+    #[test]
+    fn test_user_dict() {
+        let csv = "東京都に,1285,1285,-10000,名詞,一般,*,*,*,*,東京都に,トウキョウトニ,トーキョートニ,Tokyo";
+        let user_dict = UserDictionary::from_csv(csv).unwrap();
+        let tokenizer = Tokenizer::new().unwrap().with_user_dict(user_dict).unwrap();
+        let morphemes = tokenizer.tokenize("東京都に住む");
+        let text: Vec<_> = morphemes.iter().map(|token| &token.text).collect();
+
+        assert_eq!(vec!["東京都に", "住む"], text);
+        assert_eq!(Some("トウキョウトニ".to_owned()), morphemes[0].reading);
+        assert_eq!(Some("Tokyo"), morphemes[0].feature(9));
+        assert!(morphemes[0]
+            .raw_features()
+            .ends_with(",トーキョートニ,Tokyo"));
+    }
+
+    #[test]
+    fn test_simple_user_dict() {
+        let csv = "関西国際空港,関西 国際 空港,カンサイ コクサイ クウコウ,名詞-固有名詞";
+        let tokenizer = Tokenizer::new().unwrap();
+        let user_dict = UserDictionary::from_simple_csv(csv, &tokenizer).unwrap();
+        let tokenizer = tokenizer.with_user_dict(user_dict).unwrap();
+        let morphemes = tokenizer.tokenize("関西国際空港に行く");
+        let text: Vec<_> = morphemes.iter().map(|token| &token.text).collect();
+
+        assert_eq!(vec!["関西", "国際", "空港", "に", "行く"], text);
+        assert_eq!(Some("コクサイ".to_owned()), morphemes[1].reading);
+        assert_eq!((6, 12), (morphemes[1].start, morphemes[1].end));
+
+        let paths = tokenizer.tokenize_nbest("関西国際空港", 10);
+        let segmentations: Vec<Vec<_>> = paths
+            .iter()
+            .map(|(morphemes, _)| {
+                morphemes
+                    .iter()
+                    .map(|morpheme| (morpheme.start, morpheme.end, morpheme.raw_features()))
+                    .collect()
+            })
+            .collect();
+
+        assert!(segmentations
+            .iter()
+            .enumerate()
+            .all(|(index, segmentation)| !segmentations[..index].contains(segmentation)));
+    }
+
+    #[test]
+    fn test_tokenize_nbest() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let paths = tokenizer.tokenize_nbest("東京都に住む", 3);
+
+        assert_eq!(3, paths.len());
+        assert_eq!(tokenizer.tokenize("東京都に住む"), paths[0].0);
+        assert!(paths.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+
+    #[test]
+    fn test_lattice() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let graph = tokenizer.lattice("東京都に住む");
+        let best_path: Vec<_> = graph.best_path[1..graph.best_path.len() - 1]
+            .iter()
+            .map(|&id| match &graph.nodes[id].kind {
+                GraphNodeKind::Term { morpheme, .. } => *morpheme.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(tokenizer.tokenize("東京都に住む"), best_path);
+        assert!(graph.nodes.len() > best_path.len() + 2);
+        assert!(graph.to_dot().contains("color=red"));
+
+        let tokenizer = tokenizer.with_normalizer(Normalizer::new());
+        let graph = tokenizer.lattice("ｶﾞｽを使う");
+        let first = &graph.nodes[graph.best_path[1]];
+
+        assert_eq!((0, 9), (first.start, first.end));
+    }
+
+    #[test]
+    fn test_tokenize_with_probabilities() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let input = "東京都に住む";
+        let morphemes = tokenizer.tokenize_with_probabilities(input, 0.001);
+        let text: Vec<_> = morphemes.iter().map(|token| &token.text).collect();
+
+        assert_eq!(vec!["東京", "都", "に", "住む"], text);
+        assert!(morphemes
+            .iter()
+            .all(|m| m.probability.is_some_and(|p| p > 0.0 && p <= 1.0 + 1e-9)));
+
+        let alternatives = tokenizer.alternatives(input, 0.001);
+        let total: f64 = alternatives
+            .iter()
+            .filter(|m| m.start == 0)
+            .map(|m| m.probability.unwrap())
+            .sum();
+
+        assert!(alternatives.len() > morphemes.len());
+        assert!((total - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tokenize_with_constraints() {
+        let tokenizer = Tokenizer::new().unwrap();
+        let text = |constraints| {
+            let morphemes = tokenizer
+                .tokenize_with_constraints("東京都に住む", &constraints)
+                .unwrap();
+            morphemes.into_iter().map(|m| m.text).collect::<Vec<_>>()
+        };
+
+        let constraints = Constraints::new().with_boundary(3);
+        assert_eq!(vec!["東", "京", "都", "に", "住む"], text(constraints));
+
+        let constraints = Constraints::new().with_span(0, 9, Some(P::Noun));
+        assert_eq!(vec!["東京都", "に", "住む"], text(constraints));
+
+        let constraints = Constraints::new().with_boundary(4);
+        assert!(tokenizer
+            .tokenize_with_constraints("東京都に住む", &constraints)
+            .is_err());
+
+        let constraints = Constraints::new().with_span(0, 9, Some(P::Verb));
+        assert!(tokenizer
+            .tokenize_with_constraints("東京都に住む", &constraints)
+            .is_err());
     }
 }
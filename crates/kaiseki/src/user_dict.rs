@@ -0,0 +1,532 @@
+use crate::{
+    error::{Error, Result},
+    fst::FstSearcher,
+    tokenizer::Tokenizer,
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use mecab_types::{
+    blob::Blob,
+    dict::EntryDictionary,
+    meta::DictionaryKind,
+    row::Row,
+    term::{Term, TermId},
+};
+
+/// Cost given to the entries with a segmentation, low enough for them to be preferred over the
+/// system entries while the connection costs still apply.
+const SEGMENTATION_COST: i16 = -10000;
+
+/// Additional entries loaded alongside the system dictionary.
+///
+/// Entries either use the same CSV row format as the mecab-ipadic sources:
+///
+/// ```text
+/// surface,left_id,right_id,cost,pos,sub_pos1,sub_pos2,sub_pos3,conj_type,conj_form,base_form,reading,pronunciation
+/// ```
+///
+/// Or the UniDic one when loaded with [`DictionaryKind::Unidic`]:
+///
+/// ```text
+/// surface,left_id,right_id,cost,pos1,pos2,pos3,pos4,cType,cForm,lForm,lemma,orth,pron,...
+/// ```
+///
+/// Or a simplified format where the context id and the cost are estimated from the system
+/// dictionary, the part of speech column accepts sub parts of speech separated by `-`:
+///
+/// ```text
+/// surface,reading,pos
+/// surface,segmentation,readings,pos
+/// ```
+pub struct UserDictionary {
+    pub(crate) fst: FstSearcher,
+    pub(crate) dict: EntryDictionary,
+    segments: HashMap<TermId, Vec<Segment>>,
+}
+
+/// Number of entries and total cost of each pair of context ids.
+type ContextStats = BTreeMap<(u16, u16), (i64, i64)>;
+
+/// Context ids and costs of the system entries grouped by part of speech, used to estimate the
+/// terms of the simplified entries. Built once per tokenizer.
+pub(crate) struct TermEstimates {
+    /// The part of speech keys contain the first four feature columns each followed by a comma.
+    stats: HashMap<String, ContextStats>,
+}
+
+/// Part of a forced segmentation entry, expanded into its own morpheme.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Segment {
+    pub length: usize,
+    pub feature: String,
+}
+
+struct UserEntry<'a> {
+    surface: &'a str,
+    term: Term,
+    feature: String,
+    segments: Vec<Segment>,
+}
+
+struct SimpleEntry<'a> {
+    surface: &'a str,
+    segments: Vec<(&'a str, &'a str)>,
+    pos_prefix: String,
+    pos_columns: String,
+}
+
+impl UserDictionary {
+    /// Loads a UTF-8 encoded CSV file using the ipadic row format.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_with_kind(path, DictionaryKind::Ipadic)
+    }
+
+    /// Loads a UTF-8 encoded CSV file using the row format of the given dictionary kind.
+    pub fn load_with_kind<P: AsRef<Path>>(path: P, kind: DictionaryKind) -> Result<Self> {
+        Self::from_csv_with_kind(&fs::read_to_string(path)?, kind)
+    }
+
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        Self::from_csv_with_kind(csv, DictionaryKind::Ipadic)
+    }
+
+    pub fn from_csv_with_kind(csv: &str, kind: DictionaryKind) -> Result<Self> {
+        let mut entries = Vec::new();
+
+        for (index, line) in non_empty_lines(csv) {
+            let invalid_entry = |message: String| Error::UserDictionaryError {
+                line: index + 1,
+                message,
+            };
+            let row = Row::try_from(line).map_err(|err| invalid_entry(err.to_string()))?;
+
+            row.validate(kind)
+                .map_err(|err| invalid_entry(err.to_string()))?;
+
+            entries.push(UserEntry {
+                surface: row.surface_form,
+                term: Term::from(&row),
+                feature: row.feature.to_owned(),
+                segments: Vec::new(),
+            });
+        }
+
+        Self::build(entries)
+    }
+
+    /// Loads a UTF-8 encoded CSV file using the simplified format.
+    pub fn load_simple<P: AsRef<Path>>(path: P, tokenizer: &Tokenizer) -> Result<Self> {
+        Self::from_simple_csv(&fs::read_to_string(path)?, tokenizer)
+    }
+
+    /// Parses entries using the simplified format, context ids and costs are estimated
+    /// from the entries of the tokenizer's system dictionary and the features follow its layout.
+    pub fn from_simple_csv(csv: &str, tokenizer: &Tokenizer) -> Result<Self> {
+        Self::from_simple_csv_with_estimates(
+            csv,
+            tokenizer.term_estimates(),
+            tokenizer.metadata.kind,
+        )
+    }
+
+    fn from_simple_csv_with_estimates(
+        csv: &str,
+        estimates: &TermEstimates,
+        kind: DictionaryKind,
+    ) -> Result<Self> {
+        let mut simple_entries = Vec::new();
+
+        for (index, line) in non_empty_lines(csv) {
+            let entry = parse_simple_entry(line).map_err(|message| Error::UserDictionaryError {
+                line: index + 1,
+                message,
+            })?;
+
+            simple_entries.push((index, entry));
+        }
+
+        let mut entries = Vec::new();
+
+        for (index, entry) in &simple_entries {
+            let term = estimates.estimate(&entry.pos_prefix).ok_or_else(|| {
+                Error::UserDictionaryError {
+                    line: index + 1,
+                    message: format!("No system entry found for '{}'", entry.pos_prefix),
+                }
+            })?;
+            let build_feature = |surface: &str, reading: &str| match kind {
+                DictionaryKind::Ipadic => format!(
+                    "{},*,*,{},{},{}",
+                    entry.pos_columns, surface, reading, reading
+                ),
+                // lForm,lemma,orth,pron,orthBase,pronBase,goshu
+                DictionaryKind::Unidic => format!(
+                    "{},*,*,{},{},{},{},{},{},*",
+                    entry.pos_columns, reading, surface, surface, reading, surface, reading
+                ),
+            };
+            let reading: String = entry.segments.iter().map(|(_, reading)| *reading).collect();
+            let feature = build_feature(entry.surface, &reading);
+
+            let (term, segments) = match entry.segments.len() {
+                1 => (term, Vec::new()),
+                _ => (
+                    Term::new(term.left_id, term.right_id, SEGMENTATION_COST),
+                    entry
+                        .segments
+                        .iter()
+                        .map(|(surface, reading)| Segment {
+                            length: surface.len(),
+                            feature: build_feature(surface, reading),
+                        })
+                        .collect(),
+                ),
+            };
+
+            entries.push(UserEntry {
+                surface: entry.surface,
+                term,
+                feature,
+                segments,
+            });
+        }
+
+        Self::build(entries)
+    }
+
+    fn build(entries: Vec<UserEntry>) -> Result<Self> {
+        let mut entry_map = BTreeMap::<&str, Vec<UserEntry>>::new();
+
+        for entry in entries {
+            entry_map.entry(entry.surface).or_default().push(entry);
+        }
+
+        let fst = FstSearcher::build(entry_map.keys().copied())?;
+        let lengths: Vec<_> = entry_map.values().map(Vec::len).collect();
+        let mut terms = Vec::new();
+        let mut features = Vec::new();
+        let mut segments = HashMap::new();
+
+        for (id, entry) in entry_map.into_values().flatten().enumerate() {
+            if !entry.segments.is_empty() {
+                segments.insert(id, entry.segments);
+            }
+
+            terms.push(entry.term);
+            features.push(entry.feature);
+        }
+
+        let mut buffer = Vec::new();
+
+        EntryDictionary::encode(&terms, &features, &lengths, &mut buffer)?;
+
+        Ok(Self {
+            fst,
+            dict: EntryDictionary::new(Blob::from(buffer))?,
+            segments,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    pub(crate) fn get_segments(&self, id: TermId) -> Option<&[Segment]> {
+        self.segments.get(&id).map(Vec::as_slice)
+    }
+
+    /// Highest left and right context ids used by the entries.
+    pub(crate) fn max_context_ids(&self) -> Option<(u16, u16)> {
+        self.dict.max_context_ids()
+    }
+}
+
+fn non_empty_lines(csv: &str) -> impl Iterator<Item = (usize, &str)> {
+    csv.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+fn parse_simple_entry(line: &str) -> std::result::Result<SimpleEntry<'_>, String> {
+    let columns: Vec<_> = line.split(',').map(str::trim).collect();
+
+    let (surface, segments, pos) = match columns.as_slice() {
+        [surface, reading, pos] => (*surface, vec![(*surface, *reading)], *pos),
+        [surface, segmentation, readings, pos] => {
+            let segmentation: Vec<_> = segmentation.split_whitespace().collect();
+            let readings: Vec<_> = readings.split_whitespace().collect();
+
+            if segmentation.len() != readings.len() {
+                return Err("Segmentation and readings have different lengths".to_owned());
+            }
+
+            if segmentation.concat() != *surface {
+                return Err(format!("Segmentation doesn't match '{}'", surface));
+            }
+
+            (
+                *surface,
+                segmentation.into_iter().zip(readings).collect(),
+                *pos,
+            )
+        }
+        _ => return Err(format!("Expected 3 or 4 columns, found {}", columns.len())),
+    };
+
+    if surface.is_empty() {
+        return Err("Empty surface form".to_owned());
+    }
+
+    let mut pos_columns: Vec<_> = pos.split('-').collect();
+
+    if pos_columns.len() > 4 {
+        return Err(format!("Too many sub parts of speech in '{}'", pos));
+    }
+
+    let pos_prefix = pos_columns
+        .iter()
+        .map(|column| format!("{},", column))
+        .collect();
+
+    pos_columns.resize(4, "*");
+
+    let pos_columns = pos_columns.join(",");
+
+    Ok(SimpleEntry {
+        surface,
+        segments,
+        pos_prefix,
+        pos_columns,
+    })
+}
+
+impl TermEstimates {
+    pub fn new(dict: &EntryDictionary) -> Self {
+        let mut stats = HashMap::<String, ContextStats>::new();
+
+        for id in 0..dict.len() {
+            let (Some(feature), Some(term)) = (dict.get_raw_feature(id), dict.get_term(id)) else {
+                continue;
+            };
+
+            let pos: String = feature
+                .split(',')
+                .take(4)
+                .map(|column| format!("{},", column))
+                .collect();
+            let (count, total) = stats
+                .entry(pos)
+                .or_default()
+                .entry((term.left_id, term.right_id))
+                .or_default();
+
+            *count += 1;
+            *total += term.cost as i64;
+        }
+
+        Self { stats }
+    }
+
+    /// Picks the most frequent pair of context ids among the system entries matching a part of
+    /// speech prefix, the estimated cost is the average cost of the entries sharing these context ids.
+    pub fn estimate(&self, prefix: &str) -> Option<Term> {
+        let mut contexts = ContextStats::new();
+
+        for (pos, stats) in &self.stats {
+            if !pos.starts_with(prefix) {
+                continue;
+            }
+
+            for (ids, (count, total)) in stats {
+                let entry = contexts.entry(*ids).or_default();
+                entry.0 += count;
+                entry.1 += total;
+            }
+        }
+
+        contexts
+            .into_iter()
+            .max_by(|(id_a, (count_a, _)), (id_b, (count_b, _))| {
+                count_a.cmp(count_b).then(id_b.cmp(id_a))
+            })
+            .map(|((left_id, right_id), (count, total))| {
+                Term::new(left_id, right_id, (total / count) as i16)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mecab_types::{features::Feature, unidic::UnidicFeature};
+
+    const CSV: &str = "\
+かいせき,1285,1285,-1000,名詞,一般,*,*,*,*,かいせき,カイセキ,カイセキ
+かい,1285,1285,3000,名詞,一般,*,*,*,*,かい,カイ,カイ
+かいせき,1285,1285,2000,名詞,固有名詞,一般,*,*,*,かいせき,カイセキ,カイセキ
+";
+
+    #[test]
+    fn test_from_csv() {
+        let user_dict = UserDictionary::from_csv(CSV).unwrap();
+        let surfaces = user_dict.fst.get_from_prefix("かいせきする");
+        let base_forms: Vec<_> = surfaces
+            .iter()
+            .flat_map(|(_, index)| user_dict.dict.get_range(*index).unwrap())
+            .map(|id| user_dict.dict.get_feature(id).unwrap().base_form.unwrap())
+            .collect();
+
+        assert_eq!(3, user_dict.len());
+        assert_eq!(vec!["かい", "かいせき", "かいせき"], base_forms);
+        assert_eq!(Some((1285, 1285)), user_dict.max_context_ids());
+    }
+
+    #[test]
+    fn test_invalid_entry() {
+        let result = UserDictionary::from_csv("かいせき,1285,1285,-1000,名詞\nかい,abc");
+
+        assert!(matches!(
+            result,
+            Err(Error::UserDictionaryError { line: 2, .. })
+        ));
+        assert!(matches!(
+            UserDictionary::from_csv("テスト,1,1,100"),
+            Err(Error::UserDictionaryError { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_unidic_csv() {
+        let csv = "\
+かいせき,1285,1285,-1000,名詞,普通名詞,一般,*,*,*,カイセキ,かいせき,かいせき,カイセキ,かいせき,カイセキ,和
+かく,700,700,3000,動詞,一般,*,*,五段-カ行,終止形-一般,カク,書く,かく,カク,かく,カク,和";
+        let user_dict = UserDictionary::from_csv_with_kind(csv, DictionaryKind::Unidic).unwrap();
+
+        assert_eq!(2, user_dict.len());
+        assert!(matches!(
+            UserDictionary::from_csv_with_kind(
+                "かい,1285,1285,0,名詞,普通名詞",
+                DictionaryKind::Unidic
+            ),
+            Err(Error::UserDictionaryError { line: 1, .. })
+        ));
+        assert!(UserDictionary::from_csv_with_kind(
+            "かい,1285,1285,0,*,*,*,*,*,*",
+            DictionaryKind::Unidic
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_simple_csv() {
+        let system_terms = [
+            Term::new(3, 3, 3000),
+            Term::new(3, 3, 5000),
+            Term::new(4, 5, 100),
+        ];
+        let system_features = [
+            "名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー",
+            "名詞,固有名詞,組織,*,*,*,東大,トウダイ,トーダイ",
+            "名詞,一般,*,*,*,*,空港,クウコウ,クーコー",
+        ]
+        .map(str::to_owned);
+        let mut buffer = Vec::new();
+
+        EntryDictionary::encode(&system_terms, &system_features, &[1, 1, 1], &mut buffer).unwrap();
+
+        let system_dict = EntryDictionary::new(Blob::from(buffer)).unwrap();
+        let estimates = TermEstimates::new(&system_dict);
+        let csv = "\
+            かいせき,カイセキ,名詞-固有名詞
+            関西国際空港,関西 国際 空港,カンサイ コクサイ クウコウ,名詞-一般";
+        let user_dict =
+            UserDictionary::from_simple_csv_with_estimates(csv, &estimates, DictionaryKind::Ipadic)
+                .unwrap();
+
+        let (_, index) = user_dict.fst.get_from_prefix("かいせき")[0];
+        let id = user_dict.dict.get_range(index).unwrap().start;
+        let feature = user_dict.dict.get_feature(id).unwrap();
+
+        assert_eq!(Some(Term::new(3, 3, 4000)), user_dict.dict.get_term(id));
+        assert_eq!(Some("カイセキ".to_owned()), feature.reading);
+        assert!(user_dict.get_segments(id).is_none());
+
+        let (_, index) = user_dict.fst.get_from_prefix("関西国際空港")[0];
+        let id = user_dict.dict.get_range(index).unwrap().start;
+        let segments = user_dict.get_segments(id).unwrap();
+
+        assert_eq!(
+            Some(Term::new(4, 5, SEGMENTATION_COST)),
+            user_dict.dict.get_term(id)
+        );
+        assert_eq!(3, segments.len());
+        assert_eq!(6, segments[0].length);
+        assert_eq!(
+            "名詞,一般,*,*,*,*,国際,コクサイ,コクサイ",
+            segments[1].feature
+        );
+        assert_eq!(Some(Term::new(3, 3, 4000)), estimates.estimate("名詞,"));
+        assert!(UserDictionary::from_simple_csv_with_estimates(
+            "かいせき,カイセキ,名詞-不明",
+            &estimates,
+            DictionaryKind::Ipadic
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_simple_unidic_csv() {
+        let system_terms = [Term::new(3, 3, 3000)];
+        let system_features =
+            ["名詞,固有名詞,地名,一般,*,*,トウキョウ,東京,東京,トーキョー,東京,トーキョー,固"]
+                .map(str::to_owned);
+        let mut buffer = Vec::new();
+
+        EntryDictionary::encode(&system_terms, &system_features, &[1], &mut buffer).unwrap();
+
+        let system_dict = EntryDictionary::new(Blob::from(buffer)).unwrap();
+        let estimates = TermEstimates::new(&system_dict);
+        let user_dict = UserDictionary::from_simple_csv_with_estimates(
+            "関西国際空港,関西 国際 空港,カンサイ コクサイ クウコウ,名詞-固有名詞",
+            &estimates,
+            DictionaryKind::Unidic,
+        )
+        .unwrap();
+
+        let (_, index) = user_dict.fst.get_from_prefix("関西国際空港")[0];
+        let id = user_dict.dict.get_range(index).unwrap().start;
+        let segments = user_dict.get_segments(id).unwrap();
+        let feature = UnidicFeature::parse(user_dict.dict.get_raw_feature(id).unwrap());
+
+        assert_eq!(Some("関西国際空港".to_owned()), feature.lemma);
+        assert_eq!(
+            Some("カンサイコクサイクウコウ".to_owned()),
+            feature.lemma_reading
+        );
+        assert_eq!(
+            "名詞,固有名詞,*,*,*,*,コクサイ,国際,国際,コクサイ,国際,コクサイ,*",
+            segments[1].feature
+        );
+        assert_eq!(
+            Some("コクサイ".to_owned()),
+            Feature::from(&UnidicFeature::parse(&segments[1].feature)).reading
+        );
+    }
+
+    #[test]
+    fn test_invalid_simple_entry() {
+        assert!(parse_simple_entry("かいせき,カイセキ").is_err());
+        assert!(parse_simple_entry("関西空港,関西 国際,カンサイ クウコウ,名詞").is_err());
+        assert!(parse_simple_entry("関西空港,関西 空港,カンサイ,名詞").is_err());
+    }
+}
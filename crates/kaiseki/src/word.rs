@@ -1,4 +1,4 @@
-use crate::{inflection::Inflection, morpheme::Morpheme};
+use crate::{inflection::Inflection, morpheme::Morpheme, offset::Offsets};
 
 use std::fmt;
 
@@ -10,7 +10,11 @@ pub struct Word {
     pub start: usize,
     pub end: usize,
     pub base_form: String,
+    /// Pronunciation of the whole word, `None` if one of the morphemes has none.
+    pub pronunciation: Option<String>,
     pub class: WordClass,
+    /// Offsets covering all the morphemes of the word, set along with theirs.
+    pub offsets: Option<Offsets>,
     pub morphemes: Vec<Morpheme>,
     pub inflections: Vec<Inflection>,
 }
@@ -22,6 +26,16 @@ impl Word {
             let end = morphemes.last().map_or(main.end, |m| m.end);
             let text = morphemes.iter().map(|m| m.text.to_owned()).collect();
             let base_form = main.base_form.as_ref().unwrap_or(&main.text).to_owned();
+            let pronunciation = morphemes
+                .iter()
+                .map(|m| m.pronunciation.as_deref())
+                .collect::<Option<String>>();
+            let last_offsets = morphemes.last().and_then(|m| m.offsets);
+            let offsets = main.offsets.zip(last_offsets).map(|(first, last)| Offsets {
+                char_end: last.char_end,
+                utf16_end: last.utf16_end,
+                ..first
+            });
             let class = WordClass::from(main);
             let inflections = Inflection::from_morphemes(morphemes);
             let morphemes = morphemes.to_vec();
@@ -31,7 +45,9 @@ impl Word {
                 start,
                 end,
                 base_form,
+                pronunciation,
                 class,
+                offsets,
                 morphemes,
                 inflections,
             }
@@ -124,7 +140,7 @@ pub enum WordClass {
 }
 
 impl WordClass {
-    fn from_pos(value: PartOfSpeech) -> Self {
+    fn from_pos(value: &PartOfSpeech) -> Self {
         match value {
             PartOfSpeech::Noun => Self::Noun,
             PartOfSpeech::Verb => Self::Verb,
@@ -150,7 +166,7 @@ impl From<&Morpheme> for WordClass {
             value if value.is_counter() => Self::Counter,
             value if value.is_suffix() => Self::Suffix,
             value if value.is_expression() => Self::Expression,
-            _ => WordClass::from_pos(value.part_of_speech),
+            _ => WordClass::from_pos(&value.part_of_speech),
         }
     }
 }
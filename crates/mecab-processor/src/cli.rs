@@ -1,6 +1,9 @@
 use crate::{
+    error::{Error, Result},
     profile::{current_time, AsFileSize, SizeReport},
-    utils::{parse_category, parse_char_map, Result},
+    utils::{
+        decode_file, detect_charset, parse_category, parse_char_map, parse_encoding, parse_field,
+    },
 };
 
 use std::{
@@ -9,20 +12,33 @@ use std::{
     path::Path,
 };
 
-use bincode::Encode;
 use clap::{error::ErrorKind, CommandFactory, Parser};
-use encoding_rs::EUC_JP;
+use encoding_rs::{Encoding, EUC_JP, UTF_8};
 use fst::MapBuilder;
 use mecab_types::{
-    bincode::encode, char::CharTable, cost::CostMatrix, dict::EntryDictionary, features::Feature,
-    row::Row, term::Term, unk::UnknownDictionary, utils::FlatMatrix,
+    char::CharTable,
+    cost::CostMatrix,
+    dict::EntryDictionary,
+    meta::{DictionaryKind, Metadata},
+    row::{ColumnError, Row},
+    term::Term,
+    unk::UnknownDictionary,
+    utils::FlatMatrix,
 };
 
+/// Categories of supplementary planes characters, char.def files usually only cover the BMP.
+const SUPPLEMENTARY_CATEGORIES: [(u32, u32, &str); 2] = [
+    // Emoji and pictographs
+    (0x1F000, 0x1FAFF, "SYMBOL"),
+    // CJK Unified Ideographs Extension B and later
+    (0x20000, 0x3FFFF, "KANJI"),
+];
+
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(arg_required_else_help(true))]
 pub struct Cli {
-    /// Directory containing extracted mecab-ipadic files
+    /// Directory containing extracted mecab-ipadic or UniDic files
     #[arg(short, long)]
     input_dir: String,
 
@@ -30,11 +46,35 @@ pub struct Cli {
     #[arg(short, long)]
     out_dir: String,
 
+    /// Feature layout of the dictionary (ipadic or unidic)
+    #[arg(short, long, default_value_t = DictionaryKind::Ipadic)]
+    format: DictionaryKind,
+
+    /// Encoding of the input files, detected from the dicrc `dictionary-charset` or a BOM
+    /// by default, falling back to EUC-JP for ipadic and UTF-8 for UniDic
+    #[arg(short, long, value_parser = parse_encoding)]
+    encoding: Option<&'static Encoding>,
+
+    /// Directories of CSV entries merged on top of the input dictionary (e.g. NEologd)
+    #[arg(short, long)]
+    merge_dir: Vec<String>,
+
+    /// Encoding of the merged CSV files, detected like the input encoding, falling back to UTF-8
+    #[arg(long, value_parser = parse_encoding)]
+    merge_encoding: Option<&'static Encoding>,
+
+    /// Skip invalid lines and report every error at the end of the build
+    #[arg(short, long)]
+    keep_going: bool,
+
+    #[arg(skip)]
+    errors: Vec<Error>,
+
     #[arg(skip)]
     term_map: BTreeMap<String, Vec<Term>>,
 
     #[arg(skip)]
-    feature_map: BTreeMap<String, Vec<Feature>>,
+    feature_map: BTreeMap<String, Vec<String>>,
 
     #[arg(skip)]
     start_time: u128,
@@ -49,7 +89,7 @@ impl Cli {
         println!("Starting build...\n");
         self.start_time = current_time();
 
-        println!("Decoding mecab IPA dictionary files...");
+        println!("Decoding mecab {} dictionary files...", self.format);
         self.fill_entry_maps()?;
 
         println!("Buiding char definition..");
@@ -67,56 +107,98 @@ impl Cli {
         println!("Building entry dictionary...");
         let entry_dict = self.build_entry_dict()?;
 
+        println!("Writing metadata...");
+        let metadata = self.build_metadata()?;
+
         let report = SizeReport {
             char_def,
             unk_dict,
             cost_matrix,
             entry_dict,
             term_fst,
+            metadata,
         };
 
         self.profile(report);
 
+        if !self.errors.is_empty() {
+            eprintln!("\n{} errors found:", self.errors.len());
+
+            for error in &self.errors {
+                eprintln!("  {}", error);
+            }
+
+            return Err(Error::BuildFailed(self.errors.len()));
+        }
+
         Ok(())
     }
 
     pub fn validate_args(self) -> Result<Self> {
-        if !fs::exists(&self.input_dir)? {
-            Cli::command()
-                .error(
-                    ErrorKind::Io,
-                    format!("Directory not found '{}'", self.input_dir),
-                )
-                .exit();
+        for dir in std::iter::once(&self.input_dir).chain(&self.merge_dir) {
+            if !fs::exists(dir)? {
+                Cli::command()
+                    .error(ErrorKind::Io, format!("Directory not found '{}'", dir))
+                    .exit();
+            }
         }
 
         Ok(self)
     }
 
     fn fill_entry_maps(&mut self) -> Result<()> {
-        let mut decoded_files = Vec::new();
-        let mut rows = Vec::new();
+        let mut sources = vec![(self.input_dir.clone(), self.input_encoding()?)];
 
-        for file in self.read_csv_files()? {
-            decoded_files.push(self.read_mecab_file(&file)?);
+        for dir in &self.merge_dir {
+            let encoding = resolve_encoding(dir, self.merge_encoding, UTF_8)?;
+            sources.push((dir.clone(), encoding));
         }
 
-        for file in &decoded_files {
-            for line in file.lines() {
-                rows.push(Row::try_from(line)?);
+        // files are decoded one at a time to keep the memory usage low on large dictionaries
+        for (dir, encoding) in sources {
+            for file in read_csv_files(&dir)? {
+                let path = Path::new(&dir).join(file);
+                let file = path.display().to_string();
+                let buffer = decode_file(&path, encoding)?;
+
+                for (index, line) in buffer.lines().enumerate() {
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    let result = Row::try_from(line)
+                        .and_then(|row| self.insert_row(&row))
+                        .map_err(|err| Error::invalid_value(&file, index + 1, err));
+
+                    self.recover(result)?;
+                }
             }
         }
 
-        for row in rows {
-            self.term_map
-                .entry(row.surface_form.to_owned())
-                .or_default()
-                .push(Term::from(&row));
+        Ok(())
+    }
 
-            self.feature_map
-                .entry(row.surface_form.to_owned())
-                .or_default()
-                .push(Feature::try_from(&row)?);
+    /// Adds an entry, skipping exact duplicates which are common when merging dictionaries.
+    fn insert_row(&mut self, row: &Row) -> std::result::Result<(), ColumnError> {
+        self.validate_feature(row)?;
+
+        let term = Term::from(row);
+        let terms = self
+            .term_map
+            .entry(row.surface_form.to_owned())
+            .or_default();
+        let features = self
+            .feature_map
+            .entry(row.surface_form.to_owned())
+            .or_default();
+        let is_duplicate = terms
+            .iter()
+            .zip(features.iter())
+            .any(|(other, feature)| *other == term && feature == row.feature);
+
+        if !is_duplicate {
+            terms.push(term);
+            features.push(row.feature.to_owned());
         }
 
         Ok(())
@@ -125,13 +207,8 @@ impl Cli {
     fn build_term_fst(&self) -> Result<usize> {
         let handle = self.create_output_file("term.fst")?;
         let mut map_builder = MapBuilder::new(handle)?;
-        let mut id = 0u64;
-
-        for (key, terms) in &self.term_map {
-            let len = terms.len() as u64;
-            let value = id << 5 | len; // encode the offset, assert!(len < 32)
-            map_builder.insert(key, value)?;
-            id += len;
+        for (index, key) in self.term_map.keys().enumerate() {
+            map_builder.insert(key, index as u64)?;
         }
 
         let bytes_written = map_builder.bytes_written() as usize;
@@ -145,6 +222,8 @@ impl Cli {
         let mut terms = Vec::new();
         let mut features = Vec::new();
 
+        let lengths: Vec<_> = self.term_map.values().map(Vec::len).collect();
+
         for value in self.term_map.values() {
             terms.extend(value.clone());
         }
@@ -153,56 +232,74 @@ impl Cli {
             features.extend(value.clone());
         }
 
-        let dict = EntryDictionary::new(terms, features);
-        let bytes_written = self.write_output_file("dict.bin", dict)?;
+        let mut handle = self.create_output_file("dict.bin")?;
+        let bytes_written = EntryDictionary::encode(&terms, &features, &lengths, &mut handle)?;
 
         Ok(bytes_written)
     }
 
-    fn build_cost_matrix(&self) -> Result<usize> {
-        let buffer = self.read_mecab_file("matrix.def")?;
+    fn build_cost_matrix(&mut self) -> Result<usize> {
+        let (file, buffer) = self.read_mecab_file("matrix.def")?;
         let mut lines = buffer.lines();
-        let header = lines.next().unwrap();
-        let header: Vec<_> = header.split_whitespace().collect();
-        let row: usize = header[0].parse()?;
-        let col: usize = header[1].parse()?;
+        let header: Vec<_> = lines
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let (row, col) = parse_field(&header, 1)
+            .and_then(|row| Ok((row, parse_field(&header, 2)?)))
+            .map_err(|err| Error::invalid_value(&file, 1, err))?;
         let mut cost_matrix = FlatMatrix::new(row, col);
 
-        for line in lines {
+        for (index, line) in lines.enumerate() {
             let values: Vec<_> = line.split_whitespace().collect();
-            let right_id: usize = values[0].parse()?;
-            let left_id: usize = values[1].parse()?;
-            let cost: i16 = values[2].parse()?;
+            let result = parse_matrix_entry(&values, row, col)
+                .map_err(|err| Error::invalid_value(&file, index + 2, err));
 
-            cost_matrix.set(right_id, left_id, cost);
+            if let Some((right_id, left_id, cost)) = self.recover(result)? {
+                cost_matrix.set(right_id, left_id, cost);
+            }
         }
 
-        let cost_matrix = CostMatrix::new(cost_matrix);
-        let bytes_written = self.write_output_file("matrix.bin", cost_matrix)?;
+        let mut handle = self.create_output_file("matrix.bin")?;
+        let bytes_written = CostMatrix::encode(&cost_matrix, &mut handle)?;
 
         Ok(bytes_written)
     }
 
-    fn build_char_def(&self) -> Result<usize> {
-        let buffer = self.read_mecab_file("char.def")?;
+    fn build_char_def(&mut self) -> Result<usize> {
+        let (file, buffer) = self.read_mecab_file("char.def")?;
         let mut boundaries = Vec::new();
         let mut category_def_map = HashMap::new();
 
-        for line in buffer.lines() {
+        for (index, line) in buffer.lines().enumerate() {
+            let line = line.trim();
+
             if line.starts_with('#') || line.is_empty() {
                 continue;
             }
 
+            let locate = |err| Error::invalid_value(&file, index + 1, err);
+
             if line.starts_with("0x") {
-                let values = parse_char_map(line)?;
-                boundaries.push(values);
-            } else {
-                let (name, value) = parse_category(line)?;
+                if let Some(values) = self.recover(parse_char_map(line).map_err(locate))? {
+                    boundaries.push(values);
+                }
+            } else if let Some((name, value)) =
+                self.recover(parse_category(line).map_err(locate))?
+            {
                 category_def_map.insert(name, value);
             }
         }
 
-        let mut char_category_map = vec![vec![]; 0xFFFF];
+        let default = category_def_map.get("DEFAULT").cloned();
+        let mut char_table = CharTable::new(default.into_iter().collect());
+
+        for (lower, upper, key) in SUPPLEMENTARY_CATEGORIES {
+            if let Some(category) = category_def_map.get(key) {
+                char_table.insert(lower, upper, vec![category.clone()]);
+            }
+        }
 
         for (lower, upper, keys) in boundaries {
             let mut categories = Vec::new();
@@ -213,63 +310,88 @@ impl Cli {
                 }
             }
 
-            for index in lower..=upper {
-                char_category_map[index as usize] = categories.clone();
-            }
+            char_table.insert(lower, upper, categories);
         }
 
-        let char_table = CharTable::new(char_category_map);
-        let bytes_written = self.write_output_file("char.bin", char_table)?;
+        let mut handle = self.create_output_file("char.bin")?;
+        let bytes_written = char_table.encode(&mut handle)?;
 
         Ok(bytes_written)
     }
 
-    fn build_unk_dict(&self) -> Result<usize> {
-        let buffer = self.read_mecab_file("unk.def")?;
+    fn build_unk_dict(&mut self) -> Result<usize> {
+        let (file, buffer) = self.read_mecab_file("unk.def")?;
         let mut unk_term_map = BTreeMap::new();
-        let mut feature = Vec::new();
+        let mut features = Vec::new();
+
+        for (index, line) in buffer.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
 
-        for (id, line) in buffer.lines().enumerate() {
-            let row = Row::try_from(line)?;
-            let term = Term::from(&row);
+            let result = Row::try_from(line)
+                .and_then(|row| self.validate_feature(&row).map(|_| row))
+                .map_err(|err| Error::invalid_value(&file, index + 1, err));
+            let Some(row) = self.recover(result)? else {
+                continue;
+            };
 
             unk_term_map
                 .entry(row.surface_form.to_string())
                 .or_insert_with(Vec::new)
-                .push((id, term));
+                .push((features.len(), Term::from(&row)));
 
-            feature.push(Feature::try_from(&row)?);
+            features.push(row.feature.to_owned());
         }
 
-        let unk_dict = UnknownDictionary::new(unk_term_map, feature);
-        let bytes_written = self.write_output_file("unk.bin", unk_dict)?;
+        let unk_dict = UnknownDictionary::new(unk_term_map, features);
+        let mut handle = self.create_output_file("unk.bin")?;
+        let bytes_written = unk_dict.encode(&mut handle)?;
 
         Ok(bytes_written)
     }
 
-    fn read_csv_files(&self) -> Result<Vec<String>> {
-        let entries = fs::read_dir(&self.input_dir)?;
-        let mut files = Vec::new();
+    fn build_metadata(&self) -> Result<usize> {
+        let metadata = Metadata::new(self.format);
+        let mut handle = self.create_output_file("meta.bin")?;
+        let bytes_written = metadata.encode(&mut handle)?;
 
-        for entry in entries {
-            let file_name = entry?.file_name();
+        Ok(bytes_written)
+    }
 
-            if let Some(file_name) = file_name.to_str() {
-                if file_name.ends_with(".csv") {
-                    files.push(file_name.to_owned());
-                }
+    /// UniDic labels are kept as strings, only ipadic features are checked against the
+    /// typed model.
+    fn validate_feature(&self, row: &Row) -> std::result::Result<(), ColumnError> {
+        row.validate(self.format)
+    }
+
+    /// Records the error and skips the value in `--keep-going` mode.
+    fn recover<T>(&mut self, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.keep_going => {
+                self.errors.push(error);
+                Ok(None)
             }
+            Err(error) => Err(error),
         }
-
-        Ok(files)
     }
 
-    fn read_mecab_file(&self, filename: &str) -> Result<String> {
+    /// Returns the path of the file, used in error reports, with its content.
+    fn read_mecab_file(&self, filename: &str) -> Result<(String, String)> {
         let path = Path::new(&self.input_dir).join(filename);
-        let bytes = fs::read(path)?;
-        let (buffer, _, _) = EUC_JP.decode(&bytes);
+        let buffer = decode_file(&path, self.input_encoding()?)?;
+
+        Ok((path.display().to_string(), buffer))
+    }
+
+    fn input_encoding(&self) -> Result<&'static Encoding> {
+        let fallback = match self.format {
+            DictionaryKind::Ipadic => EUC_JP,
+            DictionaryKind::Unidic => UTF_8,
+        };
 
-        Ok(buffer.to_string())
+        resolve_encoding(&self.input_dir, self.encoding, fallback)
     }
 
     fn create_output_file(&self, filename: &str) -> Result<File> {
@@ -279,13 +401,6 @@ impl Cli {
         Ok(handle)
     }
 
-    fn write_output_file<E: Encode>(&self, filename: &str, data: E) -> Result<usize> {
-        let mut handle = self.create_output_file(filename)?;
-        let bytes_written = encode(data, &mut handle)?;
-
-        Ok(bytes_written)
-    }
-
     fn profile(&self, report: SizeReport) {
         let sections = [
             ("char.bin", report.char_def.as_file_size()),
@@ -293,6 +408,7 @@ impl Cli {
             ("cost.bin", report.cost_matrix.as_file_size()),
             ("dict.bin", report.entry_dict.as_file_size()),
             ("term.fst", report.term_fst.as_file_size()),
+            ("meta.bin", report.metadata.as_file_size()),
             ("Total", report.total().as_file_size()),
         ];
 
@@ -308,3 +424,159 @@ impl Cli {
         );
     }
 }
+
+fn read_csv_files(dir: &str) -> Result<Vec<String>> {
+    let entries = fs::read_dir(dir)?;
+    let mut files = Vec::new();
+
+    for entry in entries {
+        let file_name = entry?.file_name();
+
+        if let Some(file_name) = file_name.to_str() {
+            if file_name.ends_with(".csv") {
+                files.push(file_name.to_owned());
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn resolve_encoding(
+    dir: &str,
+    encoding: Option<&'static Encoding>,
+    fallback: &'static Encoding,
+) -> Result<&'static Encoding> {
+    match encoding {
+        Some(encoding) => Ok(encoding),
+        None => Ok(detect_charset(Path::new(dir))?.unwrap_or(fallback)),
+    }
+}
+
+fn parse_matrix_entry(
+    values: &[&str],
+    rows: usize,
+    cols: usize,
+) -> std::result::Result<(usize, usize, i16), ColumnError> {
+    let right_id: usize = parse_field(values, 1)?;
+    let left_id: usize = parse_field(values, 2)?;
+    let cost = parse_field(values, 3)?;
+
+    if right_id >= rows {
+        return Err(ColumnError::new(1, values[0], "Context id out of bounds"));
+    }
+
+    if left_id >= cols {
+        return Err(ColumnError::new(2, values[1], "Context id out of bounds"));
+    }
+
+    Ok((right_id, left_id, cost))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::tests::test_dir;
+
+    use encoding_rs::SHIFT_JIS;
+
+    #[test]
+    fn test_resolve_encoding() {
+        let dir = test_dir("resolve");
+        let path = dir.display().to_string();
+
+        assert_eq!(EUC_JP, resolve_encoding(&path, None, EUC_JP).unwrap());
+
+        fs::write(dir.join("dicrc"), "config-charset = UTF-8\n").unwrap();
+        assert_eq!(EUC_JP, resolve_encoding(&path, None, EUC_JP).unwrap());
+
+        fs::write(dir.join("dicrc"), "dictionary-charset = UTF-8\n").unwrap();
+        assert_eq!(UTF_8, resolve_encoding(&path, None, EUC_JP).unwrap());
+        assert_eq!(
+            SHIFT_JIS,
+            resolve_encoding(&path, Some(SHIFT_JIS), EUC_JP).unwrap()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn cli(input_dir: &str, keep_going: bool) -> Cli {
+        let mut args = vec!["mecab-processor", "-i", input_dir, "-o", input_dir];
+
+        if keep_going {
+            args.push("--keep-going");
+        }
+
+        Cli::parse_from(args)
+    }
+
+    #[test]
+    fn test_insert_row() {
+        let mut cli = cli(".", false);
+        let row =
+            Row::try_from("東京,1,1,100,名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー")
+                .unwrap();
+
+        cli.insert_row(&row).unwrap();
+        cli.insert_row(&row).unwrap();
+
+        assert_eq!(1, cli.term_map["東京"].len());
+
+        let error = cli.insert_row(&Row::try_from("東京,1,1,100").unwrap());
+
+        assert!(matches!(error, Err(ColumnError { column: 5, .. })));
+        assert!(matches!(
+            Row::try_from("東京,1,1"),
+            Err(ColumnError { column: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_keep_going() {
+        let dir = test_dir("keep-going");
+        let path = dir.display().to_string();
+        let csv = "\
+東京,1,1,100,名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー
+京都,1,1,abc,名詞,固有名詞,地域,一般,*,*,京都,キョウト,キョート
+大阪,1,1,100
+";
+
+        fs::write(dir.join("dicrc"), "dictionary-charset = UTF-8\n").unwrap();
+        fs::write(dir.join("entries.csv"), csv).unwrap();
+
+        assert!(matches!(
+            cli(&path, false).fill_entry_maps(),
+            Err(Error::InvalidValue {
+                line: 2,
+                column: 4,
+                ..
+            })
+        ));
+
+        let mut cli = cli(&path, true);
+
+        cli.fill_entry_maps().unwrap();
+
+        assert_eq!(vec!["東京"], cli.term_map.keys().collect::<Vec<_>>());
+        assert!(matches!(
+            cli.errors.as_slice(),
+            [
+                Error::InvalidValue {
+                    line: 2,
+                    column: 4,
+                    ..
+                },
+                Error::InvalidValue {
+                    line: 3,
+                    column: 5,
+                    ..
+                }
+            ]
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+use std::io;
+
+use bincode::error::EncodeError;
+use mecab_types::row::ColumnError;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Encode(#[from] EncodeError),
+    #[error("{0}")]
+    Fst(#[from] fst::Error),
+    #[error("Unknown encoding '{0}'")]
+    UnknownEncoding(String),
+    #[error("Malformed {encoding} bytes in '{file}' at line {line}")]
+    MalformedBytes {
+        file: String,
+        line: usize,
+        encoding: &'static str,
+    },
+    #[error("{file}:{line}:{column}: {message} ('{text}')")]
+    InvalidValue {
+        file: String,
+        line: usize,
+        column: usize,
+        text: String,
+        message: String,
+    },
+    #[error("Build failed with {0} errors")]
+    BuildFailed(usize),
+}
+
+impl Error {
+    /// Locates a column error, `line` being 1-based.
+    pub fn invalid_value(file: &str, line: usize, error: ColumnError) -> Self {
+        Self::InvalidValue {
+            file: file.to_owned(),
+            line,
+            column: error.column,
+            text: error.text,
+            message: error.message,
+        }
+    }
+}
@@ -1,14 +1,18 @@
 mod cli;
+mod error;
 mod profile;
 mod utils;
 
-use crate::utils::Result;
-
 use clap::Parser;
 use cli::Cli;
 
-fn main() -> Result<()> {
-    Cli::parse() //
-        .validate_args()?
-        .execute()
+fn main() {
+    let result = Cli::parse() //
+        .validate_args()
+        .and_then(Cli::execute);
+
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
 }
@@ -7,11 +7,17 @@ pub struct SizeReport {
     pub cost_matrix: usize,
     pub entry_dict: usize,
     pub term_fst: usize,
+    pub metadata: usize,
 }
 
 impl SizeReport {
     pub fn total(&self) -> usize {
-        self.char_def + self.unk_dict + self.cost_matrix + self.entry_dict + self.term_fst
+        self.char_def
+            + self.unk_dict
+            + self.cost_matrix
+            + self.entry_dict
+            + self.term_fst
+            + self.metadata
     }
 }
 
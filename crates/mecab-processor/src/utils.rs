@@ -1,10 +1,17 @@
-use mecab_types::char::CharCategory;
+use crate::error::{Error, Result};
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+use std::{fs, path::Path};
 
-pub fn parse_char_map(line: &str) -> Result<(u16, u16, Vec<&str>)> {
+use encoding_rs::Encoding;
+use mecab_types::{
+    char::{CharCategory, MAX_CODE_POINT},
+    row::ColumnError,
+};
+
+pub fn parse_char_map(line: &str) -> std::result::Result<(u32, u32, Vec<&str>), ColumnError> {
     let fields: Vec<_> = line.split_whitespace().collect();
-    let bounds: Vec<_> = fields[0].split("..").collect();
+    let range = get_field(&fields, 1)?;
+    let bounds: Vec<_> = range.split("..").collect();
 
     let (lower, upper) = match bounds.len() {
         1 => {
@@ -18,6 +25,10 @@ pub fn parse_char_map(line: &str) -> Result<(u16, u16, Vec<&str>)> {
         }
     };
 
+    if lower > upper || upper > MAX_CODE_POINT {
+        return Err(ColumnError::new(1, range, "Invalid code point range"));
+    }
+
     let mut categories = Vec::new();
 
     for &category in &fields[1..] {
@@ -31,22 +42,180 @@ pub fn parse_char_map(line: &str) -> Result<(u16, u16, Vec<&str>)> {
     Ok((lower, upper, categories))
 }
 
-pub fn parse_category(line: &str) -> Result<(String, CharCategory)> {
+pub fn parse_category(line: &str) -> std::result::Result<(String, CharCategory), ColumnError> {
     let fields: Vec<_> = line.split_whitespace().collect();
-    let name = fields[0].to_owned();
-    let invoke: u8 = fields[1].parse()?;
+    let name = get_field(&fields, 1)?.to_owned();
+    let invoke: u8 = parse_field(&fields, 2)?;
     let invoke = matches!(invoke, 1);
-    let group: u8 = fields[2].parse()?;
+    let group: u8 = parse_field(&fields, 3)?;
     let group = matches!(group, 1);
-    let length = fields[3].parse()?;
+    let length = parse_field(&fields, 4)?;
     let category = CharCategory::new(name.clone(), invoke, group, length);
 
     Ok((name, category))
 }
 
-fn parse_hex(hex: &str) -> Result<u16> {
+/// Returns the field at a 1-based column.
+pub fn get_field<'a>(
+    fields: &[&'a str],
+    column: usize,
+) -> std::result::Result<&'a str, ColumnError> {
+    fields
+        .get(column - 1)
+        .copied()
+        .ok_or_else(|| ColumnError::new(column, "", "Missing field"))
+}
+
+pub fn parse_field<T>(fields: &[&str], column: usize) -> std::result::Result<T, ColumnError>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    ColumnError::parse(column, get_field(fields, column)?)
+}
+
+pub fn parse_encoding(label: &str) -> std::result::Result<&'static Encoding, String> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| format!("Unknown encoding '{}'", label))
+}
+
+/// Reads the `dictionary-charset` entry of the `dicrc` file of a dictionary directory.
+pub fn detect_charset(dir: &Path) -> Result<Option<&'static Encoding>> {
+    let path = dir.join("dicrc");
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(path)?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    for line in content.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "dictionary-charset" {
+                let label = value.trim();
+                let encoding = Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| Error::UnknownEncoding(label.to_owned()))?;
+
+                return Ok(Some(encoding));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Decodes a file, a BOM takes precedence over the given encoding. Malformed bytes are
+/// reported with their line instead of being replaced.
+pub fn decode_file(path: &Path, encoding: &'static Encoding) -> Result<String> {
+    let bytes = fs::read(path)?;
+    let (encoding, bom_len) = Encoding::for_bom(&bytes).unwrap_or((encoding, 0));
+    let bytes = &bytes[bom_len..];
+
+    if let Some(decoded) = encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+        return Ok(decoded.into_owned());
+    }
+
+    let line = bytes
+        .split(|&byte| byte == b'\n')
+        .position(|line| {
+            encoding
+                .decode_without_bom_handling_and_without_replacement(line)
+                .is_none()
+        })
+        .unwrap_or_default();
+
+    Err(Error::MalformedBytes {
+        file: path.display().to_string(),
+        line: line + 1,
+        encoding: encoding.name(),
+    })
+}
+
+fn parse_hex(hex: &str) -> std::result::Result<u32, ColumnError> {
     let radix = hex.trim_start_matches("0x");
-    let parsed = u16::from_str_radix(radix, 16)?;
 
-    Ok(parsed)
+    u32::from_str_radix(radix, 16).map_err(|err| ColumnError::new(1, hex, err))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::{env, path::PathBuf, process};
+
+    use encoding_rs::{EUC_JP, SHIFT_JIS, UTF_8};
+
+    /// Creates an empty directory for the files of a test.
+    pub(crate) fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mecab-processor-{}-{}", name, process::id()));
+
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_decode_file_bom() {
+        let dir = test_dir("bom");
+        let path = dir.join("entries.csv");
+        let mut bytes = b"\xEF\xBB\xBF".to_vec();
+
+        bytes.extend_from_slice("東京,1,1,100".as_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        assert_eq!("東京,1,1,100", decode_file(&path, EUC_JP).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_decode_file_malformed() {
+        let dir = test_dir("malformed");
+        let path = dir.join("entries.csv");
+        let (line, _, _) = EUC_JP.encode("東京,1,1,100\n");
+        let mut bytes = line.repeat(2);
+
+        bytes.extend_from_slice(b"\xFF\xFE,1,1,100\n");
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(matches!(
+            decode_file(&path, EUC_JP),
+            Err(Error::MalformedBytes {
+                line: 3,
+                encoding: "EUC-JP",
+                ..
+            })
+        ));
+        assert!(matches!(
+            decode_file(&path, UTF_8),
+            Err(Error::MalformedBytes { line: 1, .. })
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_detect_charset() {
+        let dir = test_dir("charset");
+
+        assert_eq!(None, detect_charset(&dir).unwrap());
+
+        fs::write(dir.join("dicrc"), "cost-factor = 800\n").unwrap();
+        assert_eq!(None, detect_charset(&dir).unwrap());
+
+        fs::write(dir.join("dicrc"), "dictionary-charset = SHIFT-JIS\n").unwrap();
+        assert_eq!(Some(SHIFT_JIS), detect_charset(&dir).unwrap());
+
+        fs::write(dir.join("dicrc"), "dictionary-charset = unknown\n").unwrap();
+        assert!(matches!(
+            detect_charset(&dir),
+            Err(Error::UnknownEncoding(_))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -1,3 +1,5 @@
+use crate::header::Header;
+
 use std::io;
 
 use bincode::{
@@ -15,3 +17,20 @@ pub fn decode_slice<D: Decode>(bytes: &[u8]) -> Result<D, DecodeError> {
 pub fn encode<E: Encode, W: io::Write>(value: E, writer: &mut W) -> Result<usize, EncodeError> {
     bincode::encode_into_std_write(value, writer, BINCODE_CONFIG)
 }
+
+/// Decodes a value following a header, see [`Header`].
+pub fn decode_with_header<D: Decode>(header: &Header, bytes: &[u8]) -> Result<D, DecodeError> {
+    decode_slice(header.check(bytes)?)
+}
+
+pub fn encode_with_header<E: Encode, W: io::Write>(
+    header: &Header,
+    value: E,
+    writer: &mut W,
+) -> Result<usize, EncodeError> {
+    let header_len = header
+        .write(writer)
+        .map_err(|inner| EncodeError::Io { inner, index: 0 })?;
+
+    Ok(header_len + encode(value, writer)?)
+}
@@ -0,0 +1,95 @@
+use std::{fmt, ops::Deref, ops::Range, sync::Arc};
+
+use bincode::error::DecodeError;
+
+/// Shared, cheaply clonable view over a byte buffer (static data, owned bytes or a memory map).
+#[derive(Clone)]
+pub struct Blob {
+    data: Arc<dyn AsRef<[u8]> + Send + Sync>,
+    range: Range<usize>,
+}
+
+impl Blob {
+    pub fn new<T: AsRef<[u8]> + Send + Sync + 'static>(data: T) -> Self {
+        let len = data.as_ref().len();
+
+        Self {
+            data: Arc::new(data),
+            range: 0..len,
+        }
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> Result<Self, DecodeError> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(DecodeError::UnexpectedEnd {
+                additional: range.end.saturating_sub(self.len()),
+            });
+        }
+
+        Ok(Self {
+            data: self.data.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        })
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self[offset], self[offset + 1]])
+    }
+
+    pub fn read_i16(&self, offset: usize) -> i16 {
+        i16::from_le_bytes([self[offset], self[offset + 1]])
+    }
+
+    pub fn read_u32(&self, offset: usize) -> u32 {
+        let bytes = &self[offset..offset + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+impl Deref for Blob {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &(*self.data).as_ref()[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for Blob {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<&'static [u8]> for Blob {
+    fn from(value: &'static [u8]) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<Vec<u8>> for Blob {
+    fn from(value: Vec<u8>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Blob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blob").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Blob;
+
+    #[test]
+    fn test_slice() {
+        let blob = Blob::from(vec![0, 1, 2, 3, 4, 5]);
+        let slice = blob.slice(2..6).unwrap();
+
+        assert_eq!(&[2, 3, 4, 5], &*slice);
+        assert_eq!(0x0302, slice.read_u16(0));
+        assert_eq!(0x05040302, slice.read_u32(0));
+        assert!(slice.slice(2..5).is_err());
+    }
+}
@@ -1,22 +1,126 @@
-use bincode::{Decode, Encode};
+use crate::{
+    bincode::{decode_with_header, encode_with_header},
+    header::Header,
+};
 
+use std::{cmp::Ordering, io};
+
+use bincode::{
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
+
+pub const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+/// Category lookup covering all of Unicode, stored as sorted ranges of code points.
+/// Code points outside of every range use the default categories.
 #[derive(Encode, Decode)]
 pub struct CharTable {
-    map: Vec<Vec<CharCategory>>,
+    ranges: Vec<CharRange>,
+    categories: Vec<Vec<CharCategory>>,
+}
+
+/// Inclusive range of code points sharing the same categories.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct CharRange {
+    pub start: u32,
+    pub end: u32,
+    pub index: u32,
 }
 
 impl CharTable {
-    pub fn new(map: Vec<Vec<CharCategory>>) -> Self {
-        Self { map }
+    pub const HEADER: Header = Header::new("char table", b"KSKC", 1);
+
+    pub fn new(default: Vec<CharCategory>) -> Self {
+        Self {
+            ranges: Vec::new(),
+            categories: vec![default],
+        }
     }
 
-    pub fn lookup(&self, character: char) -> &[CharCategory] {
-        let index = character as usize;
+    /// Decodes the `char.bin` layout, a header followed by the bincode encoded table.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let table: Self = decode_with_header(&Self::HEADER, bytes)?;
+        let len = table.categories.len() as u32;
+
+        if len == 0 || table.ranges.iter().any(|range| range.index >= len) {
+            return Err(DecodeError::Other("invalid char table category index"));
+        }
+
+        Ok(table)
+    }
+
+    pub fn encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, EncodeError> {
+        encode_with_header(&Self::HEADER, self, writer)
+    }
 
-        match index {
-            0..=0xFFFF => &self.map[index],
-            _ => &self.map[0xFFFF],
+    /// Assigns categories to a range, overriding previous assignments.
+    pub fn insert(&mut self, start: u32, end: u32, categories: Vec<CharCategory>) {
+        let index = match self
+            .categories
+            .iter()
+            .position(|value| *value == categories)
+        {
+            Some(index) => index,
+            None => {
+                self.categories.push(categories);
+                self.categories.len() - 1
+            }
+        } as u32;
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 2);
+
+        for range in &self.ranges {
+            if range.end < start || range.start > end {
+                ranges.push(*range);
+                continue;
+            }
+
+            if range.start < start {
+                ranges.push(CharRange {
+                    end: start - 1,
+                    ..*range
+                });
+            }
+
+            if range.end > end {
+                ranges.push(CharRange {
+                    start: end + 1,
+                    ..*range
+                });
+            }
         }
+
+        ranges.push(CharRange { start, end, index });
+        ranges.sort_by_key(|range| range.start);
+        ranges.dedup_by(|next, prev| {
+            let is_adjacent = prev.end + 1 == next.start && prev.index == next.index;
+
+            if is_adjacent {
+                prev.end = next.end;
+            }
+
+            is_adjacent
+        });
+
+        self.ranges = ranges;
+    }
+
+    pub fn lookup(&self, character: char) -> &[CharCategory] {
+        let code = character as u32;
+        let index = self
+            .ranges
+            .binary_search_by(|range| {
+                if range.end < code {
+                    Ordering::Less
+                } else if range.start > code {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .map_or(0, |index| self.ranges[index].index as usize);
+
+        &self.categories[index]
     }
 }
 
@@ -38,3 +142,49 @@ impl CharCategory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_lookup() {
+        let category = |name: &str| vec![CharCategory::new(name.to_owned(), false, true, 0)];
+        let mut char_table = CharTable::new(category("DEFAULT"));
+
+        char_table.insert(0x4E00, 0x9FA5, category("KANJI"));
+        char_table.insert(0x20000, 0x2FFFF, category("KANJI"));
+        char_table.insert(0x4E00, 0x4E00, category("KANJINUMERIC"));
+        char_table.insert(0x1F000, 0x1FAFF, category("SYMBOL"));
+
+        assert_eq!(category("DEFAULT"), char_table.lookup('a'));
+        assert_eq!(category("KANJINUMERIC"), char_table.lookup('一'));
+        assert_eq!(category("KANJI"), char_table.lookup('丁'));
+        assert_eq!(category("KANJI"), char_table.lookup('𠮷'));
+        assert_eq!(category("SYMBOL"), char_table.lookup('😀'));
+        assert_eq!(category("DEFAULT"), char_table.lookup('\u{10FFFF}'));
+        assert_eq!(4, char_table.ranges.len());
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let category = vec![CharCategory::new("KANJI".to_owned(), false, false, 2)];
+        let mut char_table = CharTable::new(vec![]);
+        let mut buffer = Vec::new();
+
+        char_table.insert(0x4E00, 0x9FA5, category.clone());
+        char_table.encode(&mut buffer).unwrap();
+
+        let decoded = CharTable::decode(&buffer).unwrap();
+
+        assert_eq!(category, decoded.lookup('漢'));
+        assert!(CharTable::decode(&buffer[CharTable::HEADER.magic.len()..]).is_err());
+
+        let mut buffer = Vec::new();
+
+        char_table.ranges[0].index = 2;
+        char_table.encode(&mut buffer).unwrap();
+
+        assert!(CharTable::decode(&buffer).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 use bincode::{Decode, Encode};
 use strum::{Display, EnumString};
 
-#[derive(Debug, Display, Clone, Copy, PartialEq, Encode, Decode, EnumString)]
+/// Conjugation class of inflected words, the fifth feature column.
+#[derive(Debug, Display, Clone, PartialEq, Encode, Decode, EnumString)]
 pub enum ConjugationType {
     #[strum(serialize = "文語・ケリ")]
     BungoKeri,
@@ -119,9 +120,13 @@ pub enum ConjugationType {
     GodanGaRow,
     #[strum(serialize = "未知")]
     Unknown,
+    /// Conjugation type of another dictionary, e.g. UniDic `五段-カ行`.
+    #[strum(default)]
+    Custom(String),
 }
 
-#[derive(Debug, Display, Clone, Copy, PartialEq, Encode, Decode, EnumString)]
+/// Inflected form of a conjugated word, the sixth feature column.
+#[derive(Debug, Display, Clone, PartialEq, Encode, Decode, EnumString)]
 pub enum ConjugationForm {
     #[strum(serialize = "文語基本形")]
     ClassicalBasicForm,
@@ -179,4 +184,7 @@ pub enum ConjugationForm {
     IrrealisSpecial,
     #[strum(serialize = "未知")]
     Unknown,
+    /// Conjugation form of another dictionary, e.g. UniDic `終止形-一般`.
+    #[strum(default)]
+    Custom(String),
 }
@@ -1,18 +1,93 @@
-use crate::utils::FlatMatrix;
+use crate::{
+    blob::Blob,
+    header::{self, Header},
+    utils::FlatMatrix,
+};
 
-use bincode::{Decode, Encode};
+use std::io::{self, Write};
 
-#[derive(Encode, Decode)]
+use bincode::error::DecodeError;
+
+const HEADER_SIZE: usize = header::HEADER_SIZE + 4;
+
+/// Connection costs read in place from the `matrix.bin` layout: a [`Header`], `[rows: u16, cols: u16]`
+/// followed by `rows * cols` little-endian `i16` in row-major order.
 pub struct CostMatrix {
-    matrix: FlatMatrix<i16>,
+    values: Blob,
+    rows: usize,
+    cols: usize,
 }
 
 impl CostMatrix {
-    pub fn new(matrix: FlatMatrix<i16>) -> Self {
-        Self { matrix }
+    pub const HEADER: Header = Header::new("cost matrix", b"KSKM", 1);
+
+    pub fn new(bytes: Blob) -> Result<Self, DecodeError> {
+        Self::HEADER.check(&bytes)?;
+
+        if bytes.len() < HEADER_SIZE {
+            return Err(DecodeError::Other("invalid cost matrix header"));
+        }
+
+        let rows = bytes.read_u16(header::HEADER_SIZE) as usize;
+        let cols = bytes.read_u16(header::HEADER_SIZE + 2) as usize;
+        let values_end = rows
+            .checked_mul(cols)
+            .and_then(|count| count.checked_mul(2))
+            .and_then(|size| size.checked_add(HEADER_SIZE))
+            .ok_or(DecodeError::Other("cost matrix size overflow"))?;
+        let values = bytes.slice(HEADER_SIZE..values_end)?;
+
+        Ok(Self { values, rows, cols })
+    }
+
+    pub fn encode<W: Write>(matrix: &FlatMatrix<i16>, writer: &mut W) -> io::Result<usize> {
+        let mut buffer = Vec::with_capacity(HEADER_SIZE + matrix.values().len() * 2);
+
+        Self::HEADER.write(&mut buffer)?;
+        buffer.extend((matrix.rows() as u16).to_le_bytes());
+        buffer.extend((matrix.cols() as u16).to_le_bytes());
+
+        for value in matrix.values() {
+            buffer.extend(value.to_le_bytes());
+        }
+
+        writer.write_all(&buffer)?;
+
+        Ok(buffer.len())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
     }
 
     pub fn get(&self, right_id: u16, left_id: u16) -> i16 {
-        self.matrix.get_cloned(right_id as usize, left_id as usize)
+        let index = right_id as usize * self.cols + left_id as usize;
+        self.values.read_i16(index * 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut matrix = FlatMatrix::new(2, 3);
+        let mut buffer = Vec::new();
+
+        matrix.set(0, 2, -434);
+        matrix.set(1, 0, 120);
+
+        CostMatrix::encode(&matrix, &mut buffer).unwrap();
+
+        let cost_matrix = CostMatrix::new(Blob::from(buffer)).unwrap();
+
+        assert_eq!(-434, cost_matrix.get(0, 2));
+        assert_eq!(120, cost_matrix.get(1, 0));
+        assert_eq!(0, cost_matrix.get(1, 2));
     }
 }
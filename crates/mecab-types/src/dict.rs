@@ -1,26 +1,230 @@
 use crate::{
+    blob::Blob,
     features::Feature,
-    term::{Term, TermId},
+    header::{self, Header},
+    term::{self, Term, TermId},
 };
 
-use bincode::{Decode, Encode};
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
+
+use bincode::error::DecodeError;
+
+const HEADER_SIZE: usize = header::HEADER_SIZE + 8;
+const TERM_SIZE: usize = 6;
+const FEATURE_SIZE: usize = 8;
+const RANGE_SIZE: usize = 4;
 
-#[derive(Encode, Decode)]
+/// Term and feature tables read in place from the `dict.bin` layout:
+///
+/// ```text
+/// magic: [u8; 4], version: u32, count: u32, surfaces: u32
+/// terms: [left_id: u16, right_id: u16, cost: i16; count]
+/// features: [offset: u32, len: u32; count]
+/// ranges: [first_term: u32; surfaces + 1]
+/// string pool: [u8]
+/// ```
+///
+/// All integers are little-endian, feature offsets are relative to the string pool.
+/// The FST values are indices into the ranges table, the terms of the surface `i` being
+/// `ranges[i]..ranges[i + 1]`, so the number of homographs isn't limited.
 pub struct EntryDictionary {
-    terms: Vec<Term>,
-    features: Vec<Feature>,
+    terms: Blob,
+    features: Blob,
+    ranges: Blob,
+    pool: Blob,
+    len: usize,
+    surfaces: usize,
 }
 
 impl EntryDictionary {
-    pub fn new(terms: Vec<Term>, features: Vec<Feature>) -> Self {
-        Self { terms, features }
+    pub const HEADER: Header = Header::new("entry dictionary", b"KSKD", 1);
+
+    pub fn new(bytes: Blob) -> Result<Self, DecodeError> {
+        Self::HEADER.check(&bytes)?;
+
+        if bytes.len() < HEADER_SIZE {
+            return Err(DecodeError::Other("invalid entry dictionary header"));
+        }
+
+        let len = bytes.read_u32(header::HEADER_SIZE) as usize;
+        let surfaces = bytes.read_u32(header::HEADER_SIZE + 4) as usize;
+        let features_start = table_end(HEADER_SIZE, len, TERM_SIZE)?;
+        let ranges_start = table_end(features_start, len, FEATURE_SIZE)?;
+        let pool_start = table_end(ranges_start, surfaces.saturating_add(1), RANGE_SIZE)?;
+
+        Ok(Self {
+            terms: bytes.slice(HEADER_SIZE..features_start)?,
+            features: bytes.slice(features_start..ranges_start)?,
+            ranges: bytes.slice(ranges_start..pool_start)?,
+            pool: bytes.slice(pool_start..bytes.len())?,
+            len,
+            surfaces,
+        })
     }
 
-    pub fn get_term(&self, id: TermId) -> Option<&Term> {
-        self.terms.get(id)
+    /// Encodes the terms grouped by surface form, `lengths` being the number of terms of
+    /// each surface in the FST key order.
+    pub fn encode<W: Write>(
+        terms: &[Term],
+        features: &[String],
+        lengths: &[usize],
+        writer: &mut W,
+    ) -> io::Result<usize> {
+        let invalid_input = |message| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+        if terms.len() != features.len() || lengths.iter().sum::<usize>() != terms.len() {
+            return Err(invalid_input(
+                "The terms, features and surface lengths don't match".to_owned(),
+            ));
+        }
+
+        if terms.len() > u32::MAX as usize {
+            return Err(invalid_input(format!(
+                "Too many terms ({}), at most {} are supported",
+                terms.len(),
+                u32::MAX
+            )));
+        }
+
+        let mut buffer = Vec::new();
+        let mut pool: Vec<u8> = Vec::new();
+
+        Self::HEADER.write(&mut buffer)?;
+        buffer.extend((terms.len() as u32).to_le_bytes());
+        buffer.extend((lengths.len() as u32).to_le_bytes());
+
+        for term in terms {
+            buffer.extend(term.left_id.to_le_bytes());
+            buffer.extend(term.right_id.to_le_bytes());
+            buffer.extend(term.cost.to_le_bytes());
+        }
+
+        for feature in features {
+            buffer.extend((pool.len() as u32).to_le_bytes());
+            buffer.extend((feature.len() as u32).to_le_bytes());
+            pool.extend(feature.as_bytes());
+        }
+
+        let mut start = 0u32;
+
+        buffer.extend(start.to_le_bytes());
+
+        for &len in lengths {
+            start += len as u32;
+            buffer.extend(start.to_le_bytes());
+        }
+
+        buffer.extend(pool);
+        writer.write_all(&buffer)?;
+
+        Ok(buffer.len())
     }
 
-    pub fn get_feature(&self, id: TermId) -> Option<&Feature> {
-        self.features.get(id)
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Ids of the terms sharing the surface form stored at `index` in the FST.
+    pub fn get_range(&self, index: usize) -> Option<Range<TermId>> {
+        (index < self.surfaces).then(|| {
+            let offset = index * RANGE_SIZE;
+            let start = self.ranges.read_u32(offset) as TermId;
+            let end = self.ranges.read_u32(offset + RANGE_SIZE) as TermId;
+            start..end
+        })
+    }
+
+    pub fn get_term(&self, id: TermId) -> Option<Term> {
+        (id < self.len).then(|| {
+            let offset = id * TERM_SIZE;
+            Term::new(
+                self.terms.read_u16(offset),
+                self.terms.read_u16(offset + 2),
+                self.terms.read_i16(offset + 4),
+            )
+        })
+    }
+
+    /// Largest left and right context ids of the terms.
+    pub fn max_context_ids(&self) -> Option<(u16, u16)> {
+        term::max_context_ids((0..self.len).filter_map(|id| self.get_term(id)))
+    }
+
+    pub fn get_raw_feature(&self, id: TermId) -> Option<&str> {
+        if id >= self.len {
+            return None;
+        }
+
+        let offset = id * FEATURE_SIZE;
+        let start = self.features.read_u32(offset) as usize;
+        let end = start + self.features.read_u32(offset + 4) as usize;
+
+        self.pool
+            .get(start..end)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    pub fn get_feature(&self, id: TermId) -> Option<Feature> {
+        self.get_raw_feature(id)
+            .and_then(|feature| Feature::parse(feature).ok())
+    }
+}
+
+/// End offset of a table of `count` entries of `size` bytes, the counts being read from the
+/// header of untrusted data.
+fn table_end(start: usize, count: usize, size: usize) -> Result<usize, DecodeError> {
+    count
+        .checked_mul(size)
+        .and_then(|table_size| table_size.checked_add(start))
+        .ok_or(DecodeError::Other("entry dictionary tables overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let terms = vec![Term::new(5, 5, 6245), Term::new(3, 4, -3003)];
+        let features = vec![
+            "名詞,固有名詞,地域,一般,*,*,東,ヒガシ,ヒガシ".to_owned(),
+            "名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー".to_owned(),
+        ];
+        let mut buffer = Vec::new();
+
+        EntryDictionary::encode(&terms, &features, &[2], &mut buffer).unwrap();
+
+        let dict = EntryDictionary::new(Blob::from(buffer)).unwrap();
+
+        assert_eq!(2, dict.len());
+        assert_eq!(Some(Term::new(3, 4, -3003)), dict.get_term(1));
+        assert_eq!(Some(features[1].as_str()), dict.get_raw_feature(1));
+        assert_eq!(
+            Some("東京".to_owned()),
+            dict.get_feature(1).unwrap().base_form
+        );
+        assert_eq!(None, dict.get_term(2));
+        assert_eq!(Some((5, 5)), dict.max_context_ids());
+        assert_eq!(Some(0..2), dict.get_range(0));
+        assert_eq!(None, dict.get_range(1));
+        assert!(EntryDictionary::encode(&terms, &features, &[1], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_counts() {
+        let mut buffer = Vec::new();
+
+        EntryDictionary::HEADER.write(&mut buffer).unwrap();
+        buffer.extend(u32::MAX.to_le_bytes());
+        buffer.extend(u32::MAX.to_le_bytes());
+
+        assert!(EntryDictionary::new(Blob::from(buffer)).is_err());
     }
 }
@@ -1,9 +1,12 @@
-use crate::{conjugation::*, pos::*, row::Row};
+use crate::{
+    conjugation::*,
+    pos::*,
+    row::{ColumnError, Row},
+};
 
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
 use bincode::{Decode, Encode};
-use strum::ParseError;
 
 #[derive(Debug, Default, Clone, PartialEq, Encode, Decode)]
 pub struct Feature {
@@ -13,36 +16,121 @@ pub struct Feature {
     pub conjugation_form: Option<ConjugationForm>,
     pub base_form: Option<String>,
     pub reading: Option<String>,
+    pub pronunciation: Option<String>,
+}
+
+impl Feature {
+    /// Parses the raw feature columns as stored in the dictionary.
+    /// Columns of the errors are relative to a full row, the features starting at column 5.
+    pub fn parse(feature: &str) -> Result<Self, ColumnError> {
+        Self::try_from(&Row::from_feature(feature))
+    }
+
+    /// Formats the feature back to the ipadic columns, missing values are written as `*`.
+    pub fn to_columns(&self) -> String {
+        let mut columns = vec![self.part_of_speech.to_string()];
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "*".to_owned());
+
+        columns.extend(self.sub_part_of_speech.iter().map(ToString::to_string));
+        columns.resize(columns.len().max(4), "*".to_owned());
+        columns.extend([
+            optional(self.conjugation_type.as_ref().map(ToString::to_string)),
+            optional(self.conjugation_form.as_ref().map(ToString::to_string)),
+            optional(self.base_form.clone()),
+            optional(self.reading.clone()),
+            optional(self.pronunciation.clone()),
+        ]);
+
+        columns.join(",")
+    }
 }
 
 impl TryFrom<&Row<'_>> for Feature {
-    type Error = ParseError;
+    type Error = ColumnError;
 
     fn try_from(value: &Row) -> Result<Self, Self::Error> {
-        let sub_pos_row = &[
+        let sub_pos_row = [
             value.sub_part_of_speech1,
             value.sub_part_of_speech2,
             value.sub_part_of_speech3,
         ];
         let sub_part_of_speech = sub_pos_row
             .iter()
-            .flatten()
-            .map(|&pos| SubPartOfSpeech::from_str(pos))
+            .zip(6..)
+            .filter_map(|(pos, column)| pos.map(|pos| ColumnError::parse(column, pos)))
             .collect::<Result<_, _>>()?;
 
         Ok(Self {
             sub_part_of_speech,
-            part_of_speech: PartOfSpeech::from_str(value.part_of_speech)?,
+            part_of_speech: ColumnError::parse(5, value.part_of_speech)?,
             base_form: value.base_form.map(str::to_string),
             reading: value.reading.map(str::to_string),
-            conjugation_type: match value.conjugation_type {
-                Some(value) => Some(ConjugationType::try_from(value)?),
-                None => None,
-            },
-            conjugation_form: match value.conjugation_form {
-                Some(value) => Some(ConjugationForm::try_from(value)?),
-                None => None,
-            },
+            pronunciation: value.pronunciation.map(str::to_string),
+            conjugation_type: parse_optional(value.conjugation_type, 9)?,
+            conjugation_form: parse_optional(value.conjugation_form, 10)?,
         })
     }
 }
+
+fn parse_optional<T>(value: Option<&str>, column: usize) -> Result<Option<T>, ColumnError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .map(|value| ColumnError::parse(column, value))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bincode::{decode_slice, encode};
+
+    #[test]
+    fn test_pronunciation() {
+        let feature = Feature::parse("助詞,係助詞,*,*,*,*,は,ハ,ワ").unwrap();
+
+        assert_eq!(Some("ハ".to_owned()), feature.reading);
+        assert_eq!(Some("ワ".to_owned()), feature.pronunciation);
+        assert_eq!(
+            None,
+            Feature::parse("名詞,一般,*,*,*,*,*").unwrap().pronunciation
+        );
+    }
+
+    #[test]
+    fn test_to_columns() {
+        let raw = "動詞,自立,*,*,五段・カ行イ音便,基本形,書く,カク,カク";
+
+        assert_eq!(raw, Feature::parse(raw).unwrap().to_columns());
+        assert_eq!("その他,*,*,*,*,*,*,*,*", Feature::default().to_columns());
+    }
+
+    #[test]
+    fn test_custom_labels() {
+        let feature = Feature::parse("名詞,固有名詞,ブランド,*,独自活用,独自形,*,*").unwrap();
+
+        assert_eq!(PartOfSpeech::Noun, feature.part_of_speech);
+        assert_eq!(
+            vec![
+                SubPartOfSpeech::ProperNoun,
+                SubPartOfSpeech::Custom("ブランド".to_owned())
+            ],
+            feature.sub_part_of_speech
+        );
+        assert_eq!(
+            Some(ConjugationType::Custom("独自活用".to_owned())),
+            feature.conjugation_type
+        );
+        assert_eq!(
+            Some("独自形".to_owned()),
+            feature.conjugation_form.as_ref().map(ToString::to_string)
+        );
+
+        let mut bytes = Vec::new();
+        encode(&feature, &mut bytes).unwrap();
+
+        assert_eq!(feature, decode_slice::<Feature>(&bytes).unwrap());
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{self, Write};
+
+use bincode::error::DecodeError;
+
+pub const HEADER_SIZE: usize = 8;
+
+/// Magic and format version written at the start of the binaries, `[magic: [u8; 4], version: u32]`.
+/// Files written by an older `mecab-processor` are rejected instead of being misread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub name: &'static str,
+    pub magic: &'static [u8; 4],
+    pub version: u32,
+}
+
+impl Header {
+    pub const fn new(name: &'static str, magic: &'static [u8; 4], version: u32) -> Self {
+        Self {
+            name,
+            magic,
+            version,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_all(self.magic)?;
+        writer.write_all(&self.version.to_le_bytes())?;
+
+        Ok(HEADER_SIZE)
+    }
+
+    /// Checks the header of the bytes and returns the data following it.
+    pub fn check<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], DecodeError> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != self.magic {
+            return Err(DecodeError::OtherString(format!(
+                "Invalid {} header, the file wasn't generated by mecab-processor",
+                self.name
+            )));
+        }
+
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+        if version != self.version {
+            return Err(DecodeError::OtherString(format!(
+                "Unsupported {} version {} (expected {}), the dictionary must be regenerated",
+                self.name, version, self.version
+            )));
+        }
+
+        Ok(&bytes[HEADER_SIZE..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let header = Header::new("test", b"TEST", 2);
+        let mut bytes = Vec::new();
+
+        header.write(&mut bytes).unwrap();
+        bytes.push(42);
+
+        assert_eq!(&[42], header.check(&bytes).unwrap());
+        assert!(header.check(&bytes[..4]).is_err());
+        assert!(Header::new("test", b"TEST", 1).check(&bytes).is_err());
+        assert!(Header::new("test", b"TSET", 2).check(&bytes).is_err());
+    }
+}
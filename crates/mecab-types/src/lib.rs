@@ -1,11 +1,15 @@
 pub mod bincode;
+pub mod blob;
 pub mod char;
 pub mod conjugation;
 pub mod cost;
 pub mod dict;
 pub mod features;
+pub mod header;
+pub mod meta;
 pub mod pos;
 pub mod row;
 pub mod term;
+pub mod unidic;
 pub mod unk;
 pub mod utils;
@@ -0,0 +1,81 @@
+use crate::{
+    bincode::{decode_with_header, encode_with_header},
+    header::Header,
+};
+
+use std::io;
+
+use bincode::{
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
+use strum::{Display, EnumString};
+
+/// Feature layout of the source dictionary.
+#[derive(Debug, Default, Display, Clone, Copy, PartialEq, Eq, Encode, Decode, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum DictionaryKind {
+    #[default]
+    Ipadic,
+    Unidic,
+}
+
+/// Dictionary information written by `mecab-processor` alongside the binaries.
+#[derive(Debug, Default, Clone, PartialEq, Encode, Decode)]
+pub struct Metadata {
+    pub kind: DictionaryKind,
+}
+
+impl Metadata {
+    pub const HEADER: Header = Header::new("metadata", b"KSKI", 1);
+
+    pub fn new(kind: DictionaryKind) -> Self {
+        Self { kind }
+    }
+
+    /// Decodes the `meta.bin` layout, a header followed by the bincode encoded metadata.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        decode_with_header(&Self::HEADER, bytes)
+    }
+
+    pub fn encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, EncodeError> {
+        encode_with_header(&Self::HEADER, self, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bincode::encode;
+
+    #[test]
+    fn test_decode() {
+        let mut buffer = Vec::new();
+
+        Metadata::new(DictionaryKind::Unidic)
+            .encode(&mut buffer)
+            .unwrap();
+
+        assert_eq!(
+            DictionaryKind::Unidic,
+            Metadata::decode(&buffer).unwrap().kind
+        );
+        assert!(Metadata::decode(&[]).is_err());
+
+        let mut buffer = Vec::new();
+
+        // bare bincode written without a header
+        encode(Metadata::new(DictionaryKind::Unidic), &mut buffer).unwrap();
+
+        assert!(Metadata::decode(&buffer).is_err());
+
+        let mut buffer = Vec::new();
+
+        Header::new("metadata", b"KSKI", 2)
+            .write(&mut buffer)
+            .unwrap();
+        encode(Metadata::default(), &mut buffer).unwrap();
+
+        assert!(Metadata::decode(&buffer).is_err());
+    }
+}
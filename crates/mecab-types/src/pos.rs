@@ -1,7 +1,8 @@
 use bincode::{Decode, Encode};
 use strum::{Display, EnumString};
 
-#[derive(Debug, Default, Display, Clone, Copy, PartialEq, Encode, Decode, EnumString)]
+/// First column of the features, the ipadic parts of speech.
+#[derive(Debug, Default, Display, Clone, PartialEq, Encode, Decode, EnumString)]
 pub enum PartOfSpeech {
     #[strum(serialize = "名詞")]
     Noun,
@@ -30,9 +31,13 @@ pub enum PartOfSpeech {
     #[default]
     #[strum(serialize = "その他")]
     Other,
+    /// Part of speech of another tag set, e.g. UniDic `代名詞`.
+    #[strum(default)]
+    Custom(String),
 }
 
-#[derive(Debug, Display, Clone, Copy, PartialEq, Encode, Decode, EnumString)]
+/// Subcategories of the second to fourth feature columns.
+#[derive(Debug, Display, Clone, PartialEq, Encode, Decode, EnumString)]
 pub enum SubPartOfSpeech {
     #[strum(serialize = "一般")]
     General,
@@ -130,4 +135,7 @@ pub enum SubPartOfSpeech {
     Interjection,
     #[strum(serialize = "その他")]
     Other,
+    /// Subcategory missing from the list above, such as the ones of a user dictionary.
+    #[strum(default)]
+    Custom(String),
 }
@@ -1,4 +1,6 @@
-use std::num::ParseIntError;
+use crate::{features::Feature, meta::DictionaryKind};
+
+use std::{fmt, str::FromStr};
 
 #[derive(Debug, Default, PartialEq)]
 pub struct Row<'a> {
@@ -6,6 +8,7 @@ pub struct Row<'a> {
     pub left_id: u16,
     pub right_id: u16,
     pub cost: i16,
+    pub feature: &'a str,
     pub part_of_speech: &'a str,
     pub sub_part_of_speech1: Option<&'a str>,
     pub sub_part_of_speech2: Option<&'a str>,
@@ -14,14 +17,75 @@ pub struct Row<'a> {
     pub conjugation_form: Option<&'a str>,
     pub base_form: Option<&'a str>,
     pub reading: Option<&'a str>,
-    pub pronounciation: Option<&'a str>,
+    pub pronunciation: Option<&'a str>,
+}
+
+/// Invalid value in a line, `column` is the 1-based index of the field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnError {
+    pub column: usize,
+    pub text: String,
+    pub message: String,
+}
+
+impl ColumnError {
+    pub fn new<M: ToString>(column: usize, text: &str, message: M) -> Self {
+        Self {
+            column,
+            text: text.to_owned(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Parses a field, reporting its column on failure.
+    pub fn parse<T>(column: usize, text: &str) -> Result<T, Self>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        text.parse().map_err(|err| Self::new(column, text, err))
+    }
 }
 
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at column {} ('{}')",
+            self.message, self.column, self.text
+        )
+    }
+}
+
+impl std::error::Error for ColumnError {}
+
 impl<'a> TryFrom<&'a str> for Row<'a> {
-    type Error = ParseIntError;
+    type Error = ColumnError;
 
     fn try_from(line: &'a str) -> Result<Self, Self::Error> {
-        let values = line.split(',').collect::<Vec<_>>();
+        let mut values = line.splitn(5, ',');
+        let mut next = || values.next().unwrap_or_default();
+
+        let surface_form = next();
+        let left_id = ColumnError::parse(2, next())?;
+        let right_id = ColumnError::parse(3, next())?;
+        let cost = ColumnError::parse(4, next())?;
+        let feature = next();
+
+        Ok(Row {
+            surface_form,
+            left_id,
+            right_id,
+            cost,
+            ..Row::from_feature(feature)
+        })
+    }
+}
+
+impl<'a> Row<'a> {
+    /// Parses the feature columns of a row, the context ids and cost are left to their default.
+    pub fn from_feature(feature: &'a str) -> Self {
+        let values = feature.split(',').collect::<Vec<_>>();
 
         let get_optional = |idx: usize| {
             values
@@ -30,27 +94,56 @@ impl<'a> TryFrom<&'a str> for Row<'a> {
                 .filter(|&val| val != "*" && !val.is_empty())
         };
 
-        Ok(Row {
-            surface_form: values[0],
-            left_id: values[1].parse()?,
-            right_id: values[2].parse()?,
-            cost: values[3].parse()?,
-            part_of_speech: values[4],
-            sub_part_of_speech1: get_optional(5),
-            sub_part_of_speech2: get_optional(6),
-            sub_part_of_speech3: get_optional(7),
-            conjugation_type: get_optional(8),
-            conjugation_form: get_optional(9),
-            base_form: get_optional(10),
-            reading: get_optional(11),
-            pronounciation: get_optional(12),
-        })
+        Row {
+            feature,
+            part_of_speech: values[0],
+            sub_part_of_speech1: get_optional(1),
+            sub_part_of_speech2: get_optional(2),
+            sub_part_of_speech3: get_optional(3),
+            conjugation_type: get_optional(4),
+            conjugation_form: get_optional(5),
+            base_form: get_optional(6),
+            reading: get_optional(7),
+            pronunciation: get_optional(8),
+            ..Default::default()
+        }
+    }
+
+    /// Checks the feature columns against the layout of the dictionary, ipadic features must
+    /// match the typed model while UniDic ones only need the part of speech and conjugation
+    /// columns.
+    pub fn validate(&self, kind: DictionaryKind) -> Result<(), ColumnError> {
+        if self.part_of_speech.is_empty() || self.part_of_speech == "*" {
+            return Err(ColumnError::new(
+                5,
+                self.part_of_speech,
+                "Missing part of speech",
+            ));
+        }
+
+        match kind {
+            DictionaryKind::Ipadic => Feature::try_from(self).map(|_| ()),
+            DictionaryKind::Unidic => {
+                let columns = self.feature.split(',').count();
+
+                match columns < 6 {
+                    true => Err(ColumnError::new(
+                        4 + columns,
+                        self.feature,
+                        "Expected at least 6 feature columns",
+                    )),
+                    false => Ok(()),
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Row;
+    use super::{ColumnError, Row};
+
+    use crate::meta::DictionaryKind;
 
     #[test]
     fn test_row_parsing() {
@@ -62,6 +155,7 @@ mod tests {
             left_id: 560,
             right_id: 560,
             cost: 7716,
+            feature: "接頭詞,名詞接続,*,*,*,*,真,マ,",
             part_of_speech: "接頭詞",
             sub_part_of_speech1: Some("名詞接続"),
             sub_part_of_speech2: None,
@@ -70,9 +164,40 @@ mod tests {
             conjugation_form: None,
             base_form: Some("真"),
             reading: Some("マ"),
-            pronounciation: None,
+            pronunciation: None,
         };
 
         assert_eq!(row, expected);
     }
+
+    #[test]
+    fn test_row_error() {
+        let error = Row::try_from("真,560,abc,7716,接頭詞").unwrap_err();
+
+        assert_eq!(3, error.column);
+        assert_eq!("abc", error.text);
+        assert_eq!(
+            ColumnError::new(2, "", "cannot parse integer from empty string"),
+            Row::try_from("真").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let row =
+            Row::try_from("東京,1,1,100,名詞,固有名詞,地域,一般,*,*,東京,トウキョウ").unwrap();
+
+        assert_eq!(Ok(()), row.validate(DictionaryKind::Ipadic));
+        assert_eq!(Ok(()), row.validate(DictionaryKind::Unidic));
+
+        let row = Row::try_from("テスト,1,1,100").unwrap();
+
+        assert_eq!(5, row.validate(DictionaryKind::Ipadic).unwrap_err().column);
+        assert_eq!(5, row.validate(DictionaryKind::Unidic).unwrap_err().column);
+
+        let row = Row::try_from("東京,1,1,100,名詞,固有名詞").unwrap();
+
+        assert_eq!(Ok(()), row.validate(DictionaryKind::Ipadic));
+        assert_eq!(6, row.validate(DictionaryKind::Unidic).unwrap_err().column);
+    }
 }
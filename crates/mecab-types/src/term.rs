@@ -4,43 +4,62 @@ use bincode::{Decode, Encode};
 
 pub type TermId = usize;
 
-#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
 pub struct Term {
-    pub context_id: u16,
+    pub left_id: u16,
+    pub right_id: u16,
     pub cost: i16,
 }
 
 impl From<&Row<'_>> for Term {
     fn from(value: &Row) -> Self {
-        // NOTE: left_id and right_id should have the same value
-        // assert_eq!(value.left_id, value.right_id);
-
         Self {
-            context_id: value.left_id,
+            left_id: value.left_id,
+            right_id: value.right_id,
             cost: value.cost,
         }
     }
 }
 
 impl Term {
-    pub fn new(context_id: u16, cost: i16) -> Self {
-        Self { context_id, cost }
+    pub fn new(left_id: u16, right_id: u16, cost: i16) -> Self {
+        Self {
+            left_id,
+            right_id,
+            cost,
+        }
     }
 }
 
+/// Largest left and right context ids of the terms, `None` when there is no term.
+pub fn max_context_ids(terms: impl IntoIterator<Item = Term>) -> Option<(u16, u16)> {
+    terms
+        .into_iter()
+        .map(|term| (term.left_id, term.right_id))
+        .reduce(|(left_a, right_a), (left_b, right_b)| (left_a.max(left_b), right_a.max(right_b)))
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum TermSource {
+    #[default]
+    System,
+    User,
+    Unknown,
+}
+
 #[derive(Debug)]
 pub struct ExtractedTerm {
     pub id: TermId,
-    pub unknown: bool,
+    pub source: TermSource,
     pub length: usize,
     pub value: Term,
 }
 
 impl ExtractedTerm {
-    pub fn new(id: TermId, unknown: bool, length: usize, value: Term) -> Self {
+    pub fn new(id: TermId, source: TermSource, length: usize, value: Term) -> Self {
         Self {
             id,
-            unknown,
+            source,
             length,
             value,
         }
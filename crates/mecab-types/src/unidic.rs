@@ -0,0 +1,169 @@
+use crate::{
+    conjugation::{ConjugationForm, ConjugationType},
+    features::Feature,
+    pos::{PartOfSpeech, SubPartOfSpeech},
+};
+
+use std::str::FromStr;
+
+/// Feature columns of UniDic entries:
+///
+/// ```text
+/// pos1,pos2,pos3,pos4,cType,cForm,lForm,lemma,orth,pron,orthBase,pronBase,goshu,
+/// iType,iForm,fType,fForm,iConType,fConType,type,kana,...
+/// ```
+///
+/// Unknown word entries usually only provide the first six columns, and older releases stop
+/// before the `kana` column.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UnidicFeature {
+    pub part_of_speech: Vec<String>,
+    pub conjugation_type: Option<String>,
+    pub conjugation_form: Option<String>,
+    pub lemma_reading: Option<String>,
+    pub lemma: Option<String>,
+    pub orthographic_form: Option<String>,
+    pub pronunciation: Option<String>,
+    pub orthographic_base_form: Option<String>,
+    pub pronunciation_base_form: Option<String>,
+    /// Word origin (語種), e.g. 和, 漢, 外 or 混.
+    pub word_origin: Option<String>,
+    /// Reading of the surface form in katakana, as opposed to its pronunciation.
+    pub kana: Option<String>,
+}
+
+impl UnidicFeature {
+    pub fn parse(feature: &str) -> Self {
+        let values = feature.split(',').collect::<Vec<_>>();
+
+        let get_optional = |idx: usize| {
+            values
+                .get(idx)
+                .filter(|&&val| val != "*" && !val.is_empty())
+                .map(|&val| val.to_owned())
+        };
+
+        Self {
+            part_of_speech: (0..4).filter_map(get_optional).collect(),
+            conjugation_type: get_optional(4),
+            conjugation_form: get_optional(5),
+            lemma_reading: get_optional(6),
+            lemma: get_optional(7),
+            orthographic_form: get_optional(8),
+            pronunciation: get_optional(9),
+            orthographic_base_form: get_optional(10),
+            pronunciation_base_form: get_optional(11),
+            word_origin: get_optional(12),
+            kana: get_optional(20),
+        }
+    }
+}
+
+/// Maps the UniDic labels to the closest ipadic ones, labels without equivalent are kept as custom
+/// labels.
+impl From<&UnidicFeature> for Feature {
+    fn from(value: &UnidicFeature) -> Self {
+        let mut sub_part_of_speech = Vec::new();
+        let part_of_speech = match value.part_of_speech.first().map(String::as_str) {
+            Some("代名詞") => {
+                sub_part_of_speech.push(SubPartOfSpeech::Pronoun);
+                PartOfSpeech::Noun
+            }
+            Some("形状詞") => {
+                sub_part_of_speech.push(SubPartOfSpeech::AdjectivalNounStem);
+                PartOfSpeech::Noun
+            }
+            Some("接尾辞") => {
+                sub_part_of_speech.push(SubPartOfSpeech::Suffix);
+                PartOfSpeech::Noun
+            }
+            Some("接頭辞") => PartOfSpeech::Prefix,
+            Some("補助記号" | "空白") => PartOfSpeech::Symbol,
+            Some(pos) => PartOfSpeech::from_str(pos).unwrap_or_default(),
+            None => PartOfSpeech::default(),
+        };
+        let ipadic_label = |label: &String| label.replace('-', "・");
+
+        sub_part_of_speech.extend(
+            value
+                .part_of_speech
+                .iter()
+                .skip(1)
+                .filter_map(|pos| SubPartOfSpeech::from_str(pos).ok()),
+        );
+
+        Self {
+            part_of_speech,
+            sub_part_of_speech,
+            conjugation_type: value
+                .conjugation_type
+                .as_ref()
+                .and_then(|value| ConjugationType::from_str(&ipadic_label(value)).ok()),
+            conjugation_form: value
+                .conjugation_form
+                .as_ref()
+                .and_then(|value| ConjugationForm::from_str(&ipadic_label(value)).ok()),
+            base_form: value.orthographic_base_form.clone().or(value.lemma.clone()),
+            reading: value.kana.clone().or(value.lemma_reading.clone()),
+            pronunciation: value.pronunciation.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let raw = "動詞,一般,*,*,五段-カ行,連用形-イ音便,カク,書く,書い,カイ,書く,カク,和,*,*,*,*";
+        let feature = UnidicFeature::parse(raw);
+
+        assert_eq!(vec!["動詞", "一般"], feature.part_of_speech);
+        assert_eq!(Some("五段-カ行".to_owned()), feature.conjugation_type);
+        assert_eq!(Some("書く".to_owned()), feature.lemma);
+        assert_eq!(Some("書い".to_owned()), feature.orthographic_form);
+        assert_eq!(Some("和".to_owned()), feature.word_origin);
+
+        let feature = Feature::from(&feature);
+
+        assert_eq!(PartOfSpeech::Verb, feature.part_of_speech);
+        assert_eq!(vec![SubPartOfSpeech::General], feature.sub_part_of_speech);
+        assert_eq!(Some("書く".to_owned()), feature.base_form);
+        assert_eq!(Some("カク".to_owned()), feature.reading);
+        assert_eq!(Some("カイ".to_owned()), feature.pronunciation);
+
+        let raw = "名詞,固有名詞,地名,一般,*,*,トウキョウ,トウキョウ,東京,トーキョー,東京,トーキョー,固,*,*,*,*,*,*,固有名,トウキョウ,トウキョウ,トウキョウ,トウキョウ";
+        let feature = Feature::from(&UnidicFeature::parse(raw));
+
+        assert_eq!(Some("トウキョウ".to_owned()), feature.reading);
+        assert_eq!(Some("トーキョー".to_owned()), feature.pronunciation);
+
+        let raw = "動詞,一般,*,*,五段-カ行,連用形-イ音便,カク,書く,書い,カイ,書く,カク,和,*,*,*,*,*,*,用,カイ,カク,カイ,カク";
+        let feature = Feature::from(&UnidicFeature::parse(raw));
+
+        assert_eq!(Some("カイ".to_owned()), feature.reading);
+
+        let feature = Feature::from(&UnidicFeature::parse("形状詞,一般,*,*,*,*"));
+
+        assert_eq!(PartOfSpeech::Noun, feature.part_of_speech);
+        assert_eq!(
+            vec![
+                SubPartOfSpeech::AdjectivalNounStem,
+                SubPartOfSpeech::General
+            ],
+            feature.sub_part_of_speech
+        );
+        assert_eq!(None, feature.base_form);
+
+        let feature = Feature::from(&UnidicFeature::parse("名詞,普通名詞,一般,*,*,*"));
+
+        assert_eq!(
+            vec![
+                SubPartOfSpeech::Custom("普通名詞".to_owned()),
+                SubPartOfSpeech::General
+            ],
+            feature.sub_part_of_speech
+        );
+    }
+}
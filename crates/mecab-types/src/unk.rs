@@ -1,23 +1,41 @@
 use crate::{
+    bincode::{decode_with_header, encode_with_header},
     features::Feature,
-    term::{Term, TermId},
+    header::Header,
+    term::{self, Term, TermId},
 };
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, io};
 
-use bincode::{Decode, Encode};
+use bincode::{
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
 
 type TermMap = BTreeMap<String, Vec<(TermId, Term)>>;
 
+/// Unknown word templates per character category, the features are stored raw like the
+/// entry dictionary ones.
 #[derive(Encode, Decode)]
 pub struct UnknownDictionary {
     terms: TermMap,
-    feature: Vec<Feature>,
+    features: Vec<String>,
 }
 
 impl UnknownDictionary {
-    pub fn new(terms: TermMap, feature: Vec<Feature>) -> Self {
-        Self { terms, feature }
+    pub const HEADER: Header = Header::new("unknown dictionary", b"KSKU", 1);
+
+    pub fn new(terms: TermMap, features: Vec<String>) -> Self {
+        Self { terms, features }
+    }
+
+    /// Decodes the `unk.bin` layout, a header followed by the bincode encoded dictionary.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        decode_with_header(&Self::HEADER, bytes)
+    }
+
+    pub fn encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, EncodeError> {
+        encode_with_header(&Self::HEADER, self, writer)
     }
 }
 
@@ -26,7 +44,17 @@ impl UnknownDictionary {
         self.terms.get(key)
     }
 
-    pub fn get_feat(&self, id: TermId) -> Option<&Feature> {
-        self.feature.get(id)
+    /// Largest left and right context ids of the templates.
+    pub fn max_context_ids(&self) -> Option<(u16, u16)> {
+        term::max_context_ids(self.terms.values().flatten().map(|(_, term)| *term))
+    }
+
+    pub fn get_raw_feature(&self, id: TermId) -> Option<&str> {
+        self.features.get(id).map(String::as_str)
+    }
+
+    pub fn get_feat(&self, id: TermId) -> Option<Feature> {
+        self.get_raw_feature(id)
+            .and_then(|feature| Feature::parse(feature).ok())
     }
 }
@@ -17,18 +17,30 @@ impl<T: Default + Clone> FlatMatrix<T> {
     }
 
     pub fn set(&mut self, row: usize, col: usize, value: T) {
-        self.values[row * self.rows + col] = value;
+        self.values[row * self.cols + col] = value;
     }
 
     pub fn get(&self, row: usize, col: usize) -> &T {
-        &self.values[row * self.rows + col]
+        &self.values[row * self.cols + col]
     }
 
     pub fn get_cloned(&self, row: usize, col: usize) -> T {
-        self.values[row * self.rows + col].clone()
+        self.values[row * self.cols + col].clone()
     }
 
     pub fn get_row(&self, row: usize) -> &[T] {
-        &self.values[row..row + self.cols]
+        &self.values[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
     }
 }